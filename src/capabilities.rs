@@ -0,0 +1,69 @@
+use serde::Serialize;
+use std::error::Error;
+
+/// What a player allows (`CanGoNext`, `CanGoPrevious`, `CanPause`, `CanPlay` and `CanSeek` in MPRIS)
+#[derive(Clone, Copy, PartialEq, Serialize)]
+pub struct Capabilities {
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub can_pause: bool,
+    pub can_play: bool,
+    pub can_seek: bool,
+}
+
+impl Default for Capabilities {
+    /// Everything, when the metadata command does not tell
+    fn default() -> Self {
+        Self {
+            can_go_next: true,
+            can_go_previous: true,
+            can_pause: true,
+            can_play: true,
+            can_seek: true,
+        }
+    }
+}
+
+impl Capabilities {
+    /// Parses the capabilities field of the metadata command, e.g.: `next,previous,pause,play,seek`.
+    /// "none" if the player allows none of them, everything if the field is empty.
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim();
+        if value.is_empty() {
+            return Self::default();
+        }
+
+        let names: Vec<&str> = value.split(',').map(|v| v.trim()).collect();
+        let has = |name: &str| names.contains(&name);
+
+        Self {
+            can_go_next: has("next"),
+            can_go_previous: has("previous"),
+            can_pause: has("pause"),
+            can_play: has("play"),
+            can_seek: has("seek"),
+        }
+    }
+
+    /// Playerctl command to execute for `command` on a player in `state` (e.g.: "Playing"):
+    /// the command itself, a fallback (e.g.: stop instead of pause), or an error if the player does not allow it.
+    pub fn resolve<'a>(&self, command: &'a str, state: &str) -> Result<&'a str, Box<dyn Error>> {
+        let allowed = match command {
+            // stopping is the only way to interrupt a stream that can not be paused
+            "play-pause" if state == "Playing" && !self.can_pause => return Ok("stop"),
+            "pause" if !self.can_pause => return Ok("stop"),
+            "play-pause" if state == "Playing" => true,
+            "play-pause" | "play" => self.can_play,
+            "next" => self.can_go_next,
+            "previous" => self.can_go_previous,
+            "position" => self.can_seek,
+            _ => true,
+        };
+
+        if !allowed {
+            return Err(format!("the player does not allow '{}'", command).into());
+        }
+
+        Ok(command)
+    }
+}
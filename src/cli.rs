@@ -0,0 +1,168 @@
+use crate::output::{Button, OutputFormat};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+
+/// Command line of mpris_widget
+#[derive(Parser)]
+#[command(name = "mpris_widget", version, about = "MPRIS widget for status bars, controlled through playerctl")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Print the current player and listen for actions (default when no command is given)
+    Daemon(DaemonArgs),
+    /// Send an action to the daemon, or execute it directly if the daemon is not running
+    Action(ActionArgs),
+    /// Select the player displayed and controlled by the daemon
+    Select {
+        /// Name (e.g.: firefox) or instance (e.g.: firefox.instance3303) of the player
+        #[arg(add = ArgValueCandidates::new(player_candidates))]
+        player: String,
+    },
+    /// Print the metadata of every player as a JSON array
+    List,
+    /// Print the title, artist and album of every player before and after the rules of the configuration
+    TestRules,
+    /// Print the state of the running daemon as JSON
+    Status {
+        /// Template instead of JSON, e.g.: "{artist} - {title} ({position}/{length})".
+        /// Placeholders: player, instance, state, state_icon, player_icon, artist, title, album, art_url,
+        /// station, position, length, selection, uptime
+        #[arg(long)]
+        format: Option<String>,
+    },
+    /// Print the current line of the running daemon once, without calling playerctl
+    Print {
+        /// Status bar to print for, instead of the one of the daemon
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+    /// Print the output of the running daemon as it changes
+    Watch {
+        /// Print events as JSON Lines instead: player_added, player_removed, track_changed,
+        /// state_changed, selection_changed, seeked, volume_changed
+        #[arg(long, conflicts_with_all = ["sink", "button"])]
+        events: bool,
+        /// Print the lines of a socket sink of the configuration instead
+        #[arg(long, conflicts_with = "button")]
+        sink: Option<String>,
+        /// Print the lines of a button module instead, with the format of the daemon.
+        /// Empty if the player does not allow the action, or with a "disabled" class for Waybar
+        #[arg(long, value_enum)]
+        button: Option<Button>,
+    },
+    /// Print the completion script of a shell
+    ///
+    /// e.g.: `source <(mpris_widget completions bash)`
+    Completions {
+        shell: Shell,
+    },
+}
+
+#[derive(Args, Default)]
+pub struct DaemonArgs {
+    /// Do not listen for actions on the Unix socket
+    #[arg(long)]
+    pub no_server: bool,
+    /// Write the current player into the output file, as JSON
+    #[arg(long)]
+    pub from_output_file: bool,
+    /// Write only the name of the player into the output file, on one line
+    #[arg(long, requires = "from_output_file")]
+    pub legacy_output_file: bool,
+    /// Remove a socket left behind by a previous daemon before listening
+    #[arg(long = "clean-start")]
+    pub force_clean_start: bool,
+    /// Status bar to print for
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+    /// Print every player on one line, the selected one highlighted (see `[all_players]` in the configuration)
+    #[arg(long)]
+    pub all_players: bool,
+}
+
+#[derive(Args)]
+pub struct ActionArgs {
+    pub action: Action,
+    /// Name (e.g.: firefox) or instance (e.g.: firefox.instance3303) of the player
+    #[arg(add = ArgValueCandidates::new(player_candidates))]
+    pub player: Option<String>,
+    /// Execute the action directly instead of sending it to the daemon
+    #[arg(long)]
+    pub no_server: bool,
+    /// Without a player, control the one written in the output file
+    #[arg(long)]
+    pub from_output_file: bool,
+}
+
+/// Actions forwarded to playerctl
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Action {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+    /// Seek, raise the volume or go to the next track, depending on the player (see `[mouse]` in the configuration)
+    ScrollUp,
+    /// Seek, lower the volume or go to the previous track, depending on the player
+    ScrollDown,
+}
+
+impl Action {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Play => "play",
+            Action::Pause => "pause",
+            Action::PlayPause => "play-pause",
+            Action::Stop => "stop",
+            Action::Next => "next",
+            Action::Previous => "previous",
+            Action::ScrollUp => "scroll-up",
+            Action::ScrollDown => "scroll-down",
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        }
+    }
+}
+
+/// Names and instances of the players running while completing
+fn player_candidates() -> Vec<CompletionCandidate> {
+    let players = match crate::fetch_list_blocking() {
+        Ok(v) => v,
+        Err(_) => return vec![],
+    };
+
+    let mut candidates: Vec<CompletionCandidate> = vec![];
+
+    for data in players.iter() {
+        let help = Some(data.get_display().into());
+        if !candidates.iter().any(|c| c.get_value() == data.player.as_str()) {
+            candidates.push(CompletionCandidate::new(&data.player).help(help.clone()));
+        }
+        if data.instance != data.player {
+            candidates.push(CompletionCandidate::new(&data.instance).help(help));
+        }
+    }
+
+    candidates
+}
@@ -0,0 +1,103 @@
+use crate::{PlayerMetadata, State};
+use serde::Serialize;
+use std::time::Duration;
+
+/// Difference (in microseconds) between the position of a player and where it should be
+/// after a fetch, over which the player is considered to have seeked
+const SEEK_TOLERANCE: u64 = 2_000_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    PlayerAdded,
+    PlayerRemoved,
+    TrackChanged,
+    StateChanged,
+    SelectionChanged,
+    Seeked,
+    VolumeChanged,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::PlayerAdded => "player_added",
+            EventKind::PlayerRemoved => "player_removed",
+            EventKind::TrackChanged => "track_changed",
+            EventKind::StateChanged => "state_changed",
+            EventKind::SelectionChanged => "selection_changed",
+            EventKind::Seeked => "seeked",
+            EventKind::VolumeChanged => "volume_changed",
+        }
+    }
+}
+
+/// Change of a player between two fetches
+#[derive(Clone, Serialize)]
+pub struct Event {
+    pub event: EventKind,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    /// The player after the change (before it, if it was removed)
+    pub player: PlayerMetadata,
+    /// The player before the change (the previously selected one for a selection change)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous: Option<PlayerMetadata>,
+}
+
+impl Event {
+    pub(crate) fn create(event: EventKind, player: &PlayerMetadata, previous: Option<&PlayerMetadata>) -> Self {
+        Self { event, timestamp: crate::get_timestamp(), player: player.clone(), previous: previous.cloned() }
+    }
+}
+
+fn is_same_track(a: &PlayerMetadata, b: &PlayerMetadata) -> bool {
+    a.artist == b.artist && a.title == b.title && a.album == b.album
+}
+
+/// True if the position of the same track is not where playback would have brought it
+fn has_seeked(before: &PlayerMetadata, after: &PlayerMetadata, elapsed: Duration) -> bool {
+    let (Some(position_before), Some(position_after)) = (before.position, after.position) else {
+        return false;
+    };
+
+    let expected = match before.state {
+        State::Playing => position_before + elapsed.as_micros() as u64,
+        _ => position_before,
+    };
+
+    position_after.abs_diff(expected) > SEEK_TOLERANCE
+}
+
+/// Compares two lists of players, matched by instance.
+/// `elapsed` is the time between the two fetches.
+pub fn diff(previous: &[PlayerMetadata], current: &[PlayerMetadata], elapsed: Duration) -> Vec<Event> {
+    let mut events: Vec<Event> = vec![];
+
+    for data in previous.iter() {
+        if !current.iter().any(|v| v.instance == data.instance) {
+            events.push(Event::create(EventKind::PlayerRemoved, data, None));
+        }
+    }
+
+    for data in current.iter() {
+        match previous.iter().find(|v| v.instance == data.instance) {
+            None => events.push(Event::create(EventKind::PlayerAdded, data, None)),
+            Some(before) => {
+                if !is_same_track(before, data) {
+                    events.push(Event::create(EventKind::TrackChanged, data, Some(before)));
+                } else if has_seeked(before, data, elapsed) {
+                    events.push(Event::create(EventKind::Seeked, data, Some(before)));
+                }
+                if before.state != data.state {
+                    events.push(Event::create(EventKind::StateChanged, data, Some(before)));
+                }
+                if before.volume != data.volume {
+                    events.push(Event::create(EventKind::VolumeChanged, data, Some(before)));
+                }
+            }
+        }
+    }
+
+    events
+}
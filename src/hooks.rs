@@ -0,0 +1,102 @@
+use crate::{
+    events::{Event, EventKind},
+    settings::HooksSettings,
+};
+use std::{
+    collections::HashSet,
+    error::Error,
+    process::Stdio,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::AsyncWriteExt,
+    process::Command,
+    time::{self, Duration},
+};
+
+/// Runs the commands configured for the events in the background.
+/// A hook is skipped if its previous run for the same event has not ended.
+#[derive(Default)]
+pub struct Hooks {
+    /// Events whose hook is running
+    running: Arc<Mutex<HashSet<EventKind>>>,
+}
+
+impl Hooks {
+    pub fn run(&self, settings: &HooksSettings, event: &Event) {
+        let command = match settings.get_command(event.event) {
+            Some(v) => String::from(v),
+            None => return,
+        };
+
+        if let Ok(mut running) = self.running.lock() {
+            if !running.insert(event.event) {
+                eprintln!("Hook '{}' skipped, its previous run has not ended", event.event.as_str());
+                return;
+            }
+        }
+
+        let running = Arc::clone(&self.running);
+        let timeout = Duration::from_millis(settings.timeout);
+        let event = event.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = exec_hook(&command, &event, timeout).await {
+                eprintln!("Hook '{}' failed: {}", event.event.as_str(), err);
+            }
+            if let Ok(mut running) = running.lock() {
+                running.remove(&event.event);
+            }
+        });
+    }
+}
+
+/// Executes the command with the metadata in environment variables and as JSON on stdin.
+/// The command is killed if it does not end before `timeout`.
+async fn exec_hook(command: &str, event: &Event, timeout: Duration) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let data = &event.player;
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("MPRIS_EVENT", event.event.as_str())
+        .env("MPRIS_PLAYER", &data.player)
+        .env("MPRIS_INSTANCE", &data.instance)
+        .env("MPRIS_STATE", data.get_state_str())
+        .env("MPRIS_ARTIST", &data.artist)
+        .env("MPRIS_TITLE", &data.title)
+        .env("MPRIS_ALBUM", &data.album)
+        .env("MPRIS_ART_URL", &data.art_url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let json = serde_json::to_string(event)?;
+    let mut stdin = child.stdin.take();
+
+    let run = async {
+        if let Some(stdin) = stdin.as_mut() {
+            // the command may exit without reading its input
+            let _ = stdin.write_all(json.as_bytes()).await;
+        }
+        // close stdin
+        drop(stdin);
+
+        child.wait().await
+    };
+
+    let status = match time::timeout(timeout, run).await {
+        Ok(result) => result?,
+        Err(_) => {
+            child.kill().await?;
+            return Err(format!("killed after {} ms", timeout.as_millis()).into());
+        }
+    };
+
+    if !status.success() {
+        return Err(format!("{}", status).into());
+    }
+
+    Ok(())
+}
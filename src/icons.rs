@@ -0,0 +1,77 @@
+use serde::Deserialize;
+
+/// Built-in icon sets
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IconPreset {
+    /// Material Design icons of Nerd Fonts
+    NerdFont,
+    /// Font Awesome (also in Nerd Fonts)
+    #[default]
+    FontAwesome,
+    /// Plain ASCII, for any font
+    Ascii,
+}
+
+pub(crate) struct IconSet {
+    pub playing: &'static str,
+    pub paused: &'static str,
+    pub stopped: &'static str,
+    pub previous: &'static str,
+    pub next: &'static str,
+    /// By player name
+    pub players: &'static [(&'static str, &'static str)],
+    /// Icon of the players not listed
+    pub default_player: &'static str,
+}
+
+const NERD_FONT: IconSet = IconSet {
+    playing: "\u{f040a}",
+    paused: "\u{f03e4}",
+    stopped: "\u{f04db}",
+    previous: "\u{f04ae}",
+    next: "\u{f04ad}",
+    players: &[
+        ("spotify", "\u{f04c7}"),
+        ("firefox", "\u{f0239}"),
+        ("chromium", "\u{f02af}"),
+        ("chrome", "\u{f02af}"),
+        ("vlc", "\u{f057c}"),
+    ],
+    default_player: "\u{f075a}",
+};
+
+const FONT_AWESOME: IconSet = IconSet {
+    playing: "\u{f144}",
+    paused: "\u{f28b}",
+    stopped: "\u{f28d}",
+    previous: "\u{f048}",
+    next: "\u{f051}",
+    players: &[
+        ("spotify", "\u{f1bc}"),
+        ("firefox", "\u{f269}"),
+        ("chromium", "\u{f268}"),
+        ("chrome", "\u{f268}"),
+    ],
+    default_player: "\u{f001}",
+};
+
+const ASCII: IconSet = IconSet {
+    playing: ">",
+    paused: "||",
+    stopped: "[]",
+    previous: "|<",
+    next: ">|",
+    players: &[],
+    default_player: "",
+};
+
+impl IconPreset {
+    pub(crate) fn get_icon_set(&self) -> &'static IconSet {
+        match self {
+            IconPreset::NerdFont => &NERD_FONT,
+            IconPreset::FontAwesome => &FONT_AWESOME,
+            IconPreset::Ascii => &ASCII,
+        }
+    }
+}
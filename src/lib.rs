@@ -1,40 +1,109 @@
-use crossbeam_channel::{bounded, select, tick, Receiver};
-use std::{env, error::Error, process::{Command, Output}, os::unix::net::{UnixStream, UnixListener}, thread::{self, JoinHandle}, io::{Write, Read}, fs};
-use tokio::time::Duration;
+use std::{collections::HashMap, env, error::Error, process::Output, io::Read, fs, sync::Arc};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    process::Command,
+    sync::{broadcast, mpsc, oneshot, watch, OwnedSemaphorePermit, Semaphore},
+    signal::unix::{signal, SignalKind},
+    task::JoinHandle,
+    time::{self, Duration, Instant},
+};
+use clap::{CommandFactory, Parser, ValueEnum};
+use serde::Serialize;
+use clap_complete::env::Shells;
+
+pub mod capabilities;
+pub mod cli;
+pub mod events;
+mod hooks;
+pub mod icons;
+pub mod rules;
+pub mod output;
+pub mod playback;
+pub mod scroll;
+pub mod selection;
+pub mod settings;
+mod sinks;
+pub mod state_file;
+pub mod status;
+pub mod streams;
+mod systemd;
+pub mod template;
+
+use capabilities::Capabilities;
+use cli::{Cli, Commands, DaemonArgs};
+use events::{Event, EventKind};
+use hooks::Hooks;
+use output::{Button, Element, OutputFormat};
+use playback::{Interruption, PlaybackStack};
+use scroll::PendingScroll;
+use selection::SavedSelection;
+use settings::{IconSettings, PlayerOrder, SinkFilter, SinkKind};
+use sinks::SinkState;
+use settings::Settings;
+use state_file::StateFile;
+use status::Status;
 
 const LIST_PLAYERS_CMD: &str = "list_players_metadata";
 
 const DEFAULT_OUTPUT_FILE: &str =
+    "$HOME/.local/share/mpris-widget/state.json";
+
+/// Output file with `--legacy-output-file`
+const DEFAULT_LEGACY_OUTPUT_FILE: &str =
     "$HOME/.local/share/mpris-widget/output.txt";
 
 const SOCK_PATH: &str = "/tmp/mpris_widget.sock";
 
+/// Messages are newline-terminated and cannot be longer than this (in bytes)
+const MAX_MESSAGE_LEN: u64 = 4096;
+
+/// A client silent for longer than this is disconnected
+const CLIENT_READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Connections over this number are refused until others end
+const MAX_CLIENTS: usize = 16;
+
+/// Subscribers over this number are refused until others disconnect.
+/// They stay connected, so they do not count among the clients.
+const MAX_SUBSCRIBERS: usize = 64;
+
+/// Line written to a connection before it is closed because of the limits above
+const REFUSED_REPLY: &str = "refused: too many clients";
+
+/// Actions the daemon answers to
+const QUERY_ACTIONS: [&str; 2] = ["status", "print"];
+
+/// Events kept for a subscriber that reads slower than they are sent
+const SUBSCRIPTION_CAPACITY: usize = 64;
+
 
 pub struct InfoResponse {
     code: i32,
     display: String,
     player: String,
-    state: String,
-    instance: String
-    //artist: String,
-    //title: String,
-    //art_url: String,
-    //album: String
+    instance: String,
+    metadata: Option<PlayerMetadata>,
+    /// Every player, including the one displayed
+    players: Vec<PlayerMetadata>,
 }
 
+#[derive(Debug)]
 pub struct StreamMessage {
     action: String,
     player: String,
+    /// Where to send the answer to a query
+    reply: Option<oneshot::Sender<String>>,
 }
 
 impl StreamMessage {
     pub fn build(message: String) -> Result<StreamMessage, &'static str> {
 
-        let split_message = message.split(" ");
+        let split_message = message.split(' ');
 
         let it: Vec<_> = split_message.collect();
 
-        let action = match it.get(0) {
+        let action = match it.first() {
             Some(v) => String::from(*v),
             None => String::new()
         };
@@ -43,90 +112,80 @@ impl StreamMessage {
             None => String::new()
         };
 
-        Ok(StreamMessage { action, player })
+        Ok(StreamMessage { action, player, reply: None })
+    }
+
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    pub fn player(&self) -> &str {
+        &self.player
     }
 
     fn is_empty(&self) -> bool {
         self.action.is_empty()
     }
+
+    /// True if the client waits for an answer
+    fn is_query(&self) -> bool {
+        QUERY_ACTIONS.contains(&self.action.as_str())
+    }
 }
 
 pub struct Config {
-    action: String,
-    player: String,
-    no_server: bool,
-    from_output_file: bool,
-    force_clean_start: bool,
+    command: Commands,
 }
 
 impl Config {
-    pub fn build(mut args: impl Iterator<Item = String>) -> Result<Config, &'static str> {
-        // unnecessary first arg
-        args.next();
-
-        let mut extracted_args: Vec<String> = vec![];
-        let mut options: Vec<String> = vec![];
-
-        for arg in args {
-            if arg.starts_with("--") {
-                // it's an optional argument
-                options.push(arg);
-            } else {
-                // it's an argument
-                extracted_args.push(arg);
-            }
-        }
-
-        let mut extracted_args_iter = extracted_args.into_iter();
-        let options_iter = options.into_iter();
-
-        // play_pause, previous, next, select
-        let action = match extracted_args_iter.next() {
-            Some(v) => v,
-            None => String::new(), // arguments are optional so do not return Err
-        };
-        // e.g.: spotify, musikcube, ...
-        let player = match extracted_args_iter.next() {
-            Some(v) => v,
-            None => String::new(), // arguments are optional so do not return Err
-        };
+    pub fn build(args: impl Iterator<Item = String>) -> Result<Config, clap::Error> {
+        let cli = Cli::try_parse_from(args)?;
 
-        let mut no_server = false;
-        let mut from_output_file = false;
-        let mut force_clean_start = false;
-
-        for arg in options_iter {
-            if arg.starts_with("--no-server") {
-                no_server = true;
-            } else if arg.starts_with("--from-output-file") {
-                from_output_file = true;
-            }  else if arg == "--clean-start" {
-                force_clean_start = true;
-            }
-        }
+        // run the daemon when no command is given
+        let command = cli.command.unwrap_or_else(|| Commands::Daemon(DaemonArgs::default()));
 
-        Ok(Config { action, player, no_server, from_output_file, force_clean_start })
+        Ok(Config { command })
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Serialize)]
 enum State {
     Playing,
     Paused,
     Stopped,
 }
 
+#[derive(Clone, Serialize)]
 pub struct PlayerMetadata {
     state: State,
     artist: String,
     title: String,
     art_url: String,
     album: String,
-    player: String,
-    instance: String,
-
+    pub player: String,
+    pub instance: String,
+    /// In microseconds, if given by the metadata command
+    position: Option<u64>,
+    /// In microseconds, if given by the metadata command
+    length: Option<u64>,
+    /// From 0.0 to 1.0, if given by the metadata command
+    volume: Option<f64>,
+    /// `xesam:url`, if given by the metadata command
+    url: String,
+    /// Name of the internet radio, for the players whose stream titles are split
+    station: String,
+    capabilities: Capabilities,
+
+    /// True if the artist was split from the title of a stream
+    #[serde(skip)]
+    title_split: bool,
+    #[serde(skip)]
     separator: String,
+    #[serde(skip)]
     state_paused: String,
+    #[serde(skip)]
     state_playing: String,
+    #[serde(skip)]
     state_stopped: String,
 }
 
@@ -148,16 +207,41 @@ impl PlayerMetadata {
             album:      String::from(album),
             player: String::from(player),
             instance: String::from(instance),
-
+            position: None,
+            length: None,
+            volume: None,
+            url: String::new(),
+            station: String::new(),
+            capabilities: Capabilities::default(),
+
+            title_split: false,
             separator: String::from(" - "),
-            state_paused: String::from(" "),
-            state_playing: String::from(""),
-            state_stopped: String::from(" "),
+            state_paused: String::new(),
+            state_playing: String::new(),
+            state_stopped: String::new(),
         }
+        .with_state_icons(&IconSettings::default())
     }
 
-    fn create_from_vec(metadata: &Vec<&str>) -> Result<Self, Box<dyn Error>> {
-        let result = Self::create(
+    /// Sets the icons prepended to the display of each state
+    pub(crate) fn with_state_icons(mut self, icons: &IconSettings) -> Self {
+        let prefix = |state: &str| match icons.get_state_icon(state) {
+            "" => String::new(),
+            icon => String::from(icon) + " ",
+        };
+        self.state_paused = prefix("Paused");
+        self.state_playing = prefix("Playing");
+        self.state_stopped = prefix("Stopped");
+        self
+    }
+
+    /// Creates the metadata from the fields of a line printed by the metadata command:
+    ///
+    /// `status;artist;title;art_url;album;track_id;player;instance[;position;length;volume;url;capabilities]`
+    ///
+    /// The fields between brackets are optional (see contrib/list_players_metadata).
+    fn create_from_vec(metadata: &[&str]) -> Result<Self, Box<dyn Error>> {
+        let mut result = Self::create(
             match metadata.get(6) {
                 Some(value) => value.trim(),
                 _ => return Err("Could not extract player's name".into()),
@@ -166,7 +250,7 @@ impl PlayerMetadata {
                 Some(value) => value.trim(),
                 _ => return Err("Could not extract instance's name".into()),
             },
-            match metadata.get(0) {
+            match metadata.first() {
                 Some(value) => value.trim(),
                 _ => return Err("Could not extract player's state".into()),
             },
@@ -188,6 +272,12 @@ impl PlayerMetadata {
             }
         );
 
+        result.position = metadata.get(8).and_then(|v| v.trim().parse().ok());
+        result.length = metadata.get(9).and_then(|v| v.trim().parse().ok());
+        result.volume = metadata.get(10).and_then(|v| v.trim().parse().ok());
+        result.url = metadata.get(11).map(|v| String::from(v.trim())).unwrap_or_default();
+        result.capabilities = metadata.get(12).map(|v| Capabilities::parse(v)).unwrap_or_default();
+
         Ok(result)
     }
 
@@ -199,7 +289,7 @@ impl PlayerMetadata {
         }
     }
 
-    fn get_display(&self) -> String {
+    pub fn get_display(&self) -> String {
         let mut result = String::from("");
 
         let state_display = match self.state {
@@ -209,7 +299,8 @@ impl PlayerMetadata {
         };
 
         if !self.artist.is_empty() {
-            if self.player != "mpv" {
+            // mpv only shows the title, unless the artist comes from a stream title
+            if self.player != "mpv" || self.title_split {
                 result.push_str(&self.artist);
                 result.push_str(&self.separator);
             }
@@ -230,14 +321,6 @@ impl PlayerMetadata {
     }
 }
 
-pub fn escape(v: &String) -> String {
-    v.replace(r#"""#, r#"\""#)
-}
-
-pub fn escape_ampersand(v: &String) -> String {
-    v.replace(r#"&"#, r#"&amp;"#)
-}
-
 pub fn get_playerctl_cmd() -> String {
     env::var("PLAYERCTL_PATH").unwrap_or_else(|_| String::from("playerctl"))
 }
@@ -247,44 +330,58 @@ pub fn get_players_metadata_cmd() -> String {
 }
 
 pub fn get_output_file_path() -> String {
+    get_output_file_path_of(DEFAULT_OUTPUT_FILE)
+}
+
+pub fn get_legacy_output_file_path() -> String {
+    get_output_file_path_of(DEFAULT_LEGACY_OUTPUT_FILE)
+}
+
+fn get_output_file_path_of(default: &str) -> String {
     let mut options = envmnt::ExpandOptions::new();
     options.expansion_type = Some(envmnt::ExpansionType::Unix);
-    let parsed_default = envmnt::expand(DEFAULT_OUTPUT_FILE, Some(options));
-    env::var("MPRIS_OUTPUT_FILE").unwrap_or_else(|_| String::from(parsed_default))
+    let parsed_default = envmnt::expand(default, Some(options));
+    env::var("MPRIS_OUTPUT_FILE").unwrap_or(parsed_default)
 }
 
-fn ctrl_channel() -> Result<Receiver<()>, ctrlc::Error> {
-    let (sender, receiver) = bounded(100);
-    ctrlc::set_handler(move || {
-        let _ = sender.send(());
-    })?;
+/// Milliseconds since the Unix epoch
+pub(crate) fn get_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|v| v.as_millis() as u64)
+        .unwrap_or_default()
+}
 
-    Ok(receiver)
+/// Value of an XDG base directory variable (e.g.: XDG_CONFIG_HOME), or `default` if unset
+pub(crate) fn get_xdg_dir(var: &str, default: &str) -> String {
+    match env::var(var) {
+        Ok(v) if !v.is_empty() => v,
+        _ => {
+            let mut options = envmnt::ExpandOptions::new();
+            options.expansion_type = Some(envmnt::ExpansionType::Unix);
+            envmnt::expand(default, Some(options))
+        }
+    }
 }
 
-fn exec_get_players_metadata_cmd() -> Result<Output, Box<dyn Error>> {
+async fn exec_get_players_metadata_cmd() -> Result<Output, Box<dyn Error>> {
     let cmd_path = get_players_metadata_cmd();
-    let output = Command::new("sh").arg("-c").arg(cmd_path).output()?;
+    // killed if the daemon stops while waiting for it
+    let output = Command::new("sh").arg("-c").arg(cmd_path).kill_on_drop(true).output().await?;
 
     Ok(output)
 }
 
-async fn fetch_list() -> Result<Vec<PlayerMetadata>, Box<dyn Error>> {
-
-    let output = exec_get_players_metadata_cmd()?;
-
-    let output_string = String::from_utf8(output.stdout).unwrap();
-
-    let players_list: Vec<&str> = output_string.split("\n").collect();
-
+/// Parses the output of the metadata command, one player per line
+pub fn parse_players_metadata(output_string: &str) -> Result<Vec<PlayerMetadata>, Box<dyn Error>> {
     let mut players: Vec<PlayerMetadata> = vec![];
 
-    for data in players_list {
-        if data.len() < 1 {
+    for data in output_string.split('\n') {
+        if data.is_empty() {
             break;
         }
 
-        let metadata: Vec<&str> = data.split(" ;").collect();
+        let metadata: Vec<&str> = data.split(';').collect();
 
         let formatted_data = PlayerMetadata::create_from_vec(&metadata)?;
 
@@ -294,44 +391,158 @@ async fn fetch_list() -> Result<Vec<PlayerMetadata>, Box<dyn Error>> {
     Ok(players)
 }
 
-async fn fetch_data(selected_player: &String) -> Result<(Option<i32>, Option<PlayerMetadata>, String), Box<dyn Error>> {
+/// Rewrites the metadata with the rules, and sets the icons
+fn apply_settings(players: Vec<PlayerMetadata>, settings: &Settings) -> Vec<PlayerMetadata> {
+    players
+        .into_iter()
+        .map(|mut data| {
+            streams::apply(&settings.streams, &mut data);
+            rules::apply(&settings.rules, &mut data);
+            data.with_state_icons(&settings.icons)
+        })
+        .collect()
+}
 
-    let output = exec_get_players_metadata_cmd()?;
+async fn fetch_list() -> Result<Vec<PlayerMetadata>, Box<dyn Error>> {
 
-    let output_string = String::from_utf8(output.stdout).unwrap();
+    let output = exec_get_players_metadata_cmd().await?;
 
-    let players_list: Vec<&str> = output_string.split("\n").collect();
+    let output_string = String::from_utf8(output.stdout)?;
 
-    let mut first_display = String::new();
-    let mut first_player: Option<PlayerMetadata> = None;
+    parse_players_metadata(&output_string)
+}
 
-    for data in players_list {
-        if data.len() < 1 {
-            break;
-        }
+/// Same as `fetch_list` for callers outside of the runtime (e.g.: shell completions)
+pub fn fetch_list_blocking() -> Result<Vec<PlayerMetadata>, Box<dyn Error>> {
 
-        let metadata: Vec<&str> = data.split(";").collect();
+    let cmd_path = get_players_metadata_cmd();
+    let output = std::process::Command::new("sh").arg("-c").arg(cmd_path).output()?;
 
-        let formatted_data = PlayerMetadata::create_from_vec(&metadata)?;
+    let output_string = String::from_utf8(output.stdout)?;
 
-        let is_selected_player = formatted_data.instance.eq(selected_player);
-        let is_selected_instance = formatted_data.instance.eq(selected_player);
+    parse_players_metadata(&output_string)
+}
 
-        if is_selected_player || is_selected_instance || first_player.is_none() {
-            first_display = formatted_data.get_display();
-            first_player = Some(formatted_data);
-            if is_selected_player {
-                break;
-            }
+/// Fetches the players and picks the one to display: the one matching `selected_instance`,
+/// else a player named `selected_player` (its instance may have changed), else the first listed.
+async fn fetch_data(selected_instance: &str, selected_player: &str, settings: &Settings) -> Result<(Option<i32>, Option<PlayerMetadata>, String, Vec<PlayerMetadata>), Box<dyn Error>> {
+
+    let output = exec_get_players_metadata_cmd().await?;
+
+    let output_string = String::from_utf8(output.stdout)?;
+
+    let players = apply_settings(parse_players_metadata(&output_string)?, settings);
+
+    let index = selection::pick_player(&players, selected_instance, selected_player);
+
+    let first_player = players.get(index).cloned();
+
+    let first_display = match &first_player {
+        Some(data) => data.get_display(),
+        None => String::new(),
+    };
+
+    Ok((output.status.code(), first_player, first_display, players))
+}
+
+/// Sends a message to the server, then waits for it to close the connection,
+/// so a connection refused by the server is an error
+async fn send_message_to_server(message: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut stream = UnixStream::connect(SOCK_PATH).await?;
+    stream.write_all(message).await?;
+    stream.write_all(b"\n").await?;
+    stream.shutdown().await?;
+
+    let mut response = String::new();
+    match time::timeout(CLIENT_READ_TIMEOUT, stream.read_to_string(&mut response)).await {
+        Ok(result) => result?,
+        Err(_) => return Err("the daemon did not answer".into()),
+    };
+
+    if !response.is_empty() {
+        return Err(String::from(response.trim_end()).into());
+    }
+    Ok(())
+}
+
+/// Sends a query to the server and returns its answer
+async fn query_server(message: &[u8]) -> Result<String, Box<dyn Error>> {
+    let stream = match UnixStream::connect(SOCK_PATH).await {
+        Ok(v) => v,
+        Err(err) => return Err(format!("could not reach the daemon ({}), is it running?", err).into()),
+    };
+
+    let mut reader = BufReader::new(stream);
+    reader.get_mut().write_all(message).await?;
+    reader.get_mut().write_all(b"\n").await?;
+
+    let mut response = String::new();
+    match time::timeout(CLIENT_READ_TIMEOUT, reader.read_line(&mut response)).await {
+        Ok(result) => result?,
+        Err(_) => return Err("the daemon did not answer".into()),
+    };
+
+    if response.is_empty() {
+        return Err("the daemon closed the connection".into());
+    }
+    if response.trim_end() == REFUSED_REPLY {
+        return Err(REFUSED_REPLY.into());
+    }
+
+    Ok(String::from(response.trim_end()))
+}
+
+/// Prints the lines published by the daemon as they come
+async fn exec_watch_action(subscription: &str) -> Result<(), Box<dyn Error>> {
+    let stream = match UnixStream::connect(SOCK_PATH).await {
+        Ok(v) => v,
+        Err(err) => return Err(format!("could not reach the daemon ({}), is it running?", err).into()),
+    };
+
+    let mut reader = BufReader::new(stream);
+    let message = String::from("subscribe ") + subscription + "\n";
+    reader.get_mut().write_all(message.as_bytes()).await?;
+
+    let mut stdout = tokio::io::stdout();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err("the daemon stopped".into());
+        }
+        if line.trim_end() == REFUSED_REPLY {
+            return Err(REFUSED_REPLY.into());
+        }
+        stdout.write_all(line.as_bytes()).await?;
+        stdout.flush().await?;
+    }
+}
+
+/// Prints the state of the running daemon as JSON, or formatted with a template
+async fn exec_status_action(format: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let response = query_server(b"status").await?;
+
+    match format {
+        Some(template) => {
+            let status: Status = serde_json::from_str(&response)?;
+            println!("{}", status.format(template)?);
         }
+        None => println!("{}", response),
     }
 
-    Ok((output.status.code(), first_player, first_display))
+    Ok(())
 }
 
-fn send_message_to_server(message: &[u8]) -> Result<(), Box<dyn Error>> {
-    let mut stream = UnixStream::connect(SOCK_PATH)?;
-    stream.write_all(message)?;
+/// Prints the current line of the running daemon, in its format or in `format`
+async fn exec_print_action(format: Option<OutputFormat>) -> Result<(), Box<dyn Error>> {
+    let format_name = match format.and_then(|v| v.to_possible_value()) {
+        Some(v) => String::from(v.get_name()),
+        None => String::new(),
+    };
+    let message: Vec<u8> = [b"print ", format_name.as_bytes()].concat();
+
+    let response = query_server(message.as_slice()).await?;
+    println!("{}", response);
+
     Ok(())
 }
 
@@ -342,133 +553,179 @@ fn send_message_to_server(message: &[u8]) -> Result<(), Box<dyn Error>> {
 /// * `action_name` - Command for playerctl (e.g.: play-pause, previous, next, ...)
 /// * `player` - Name of the player (e.g.: firefox) or instance (e.g.: firefox.instance3303).
 /// * `from_output_file` - If true and 'player' argument is empty, look for the player in a file.
-pub fn exec_action(action_name: &String, player: &String, from_output_file: bool) -> Result<(), Box<dyn Error>> {
+pub async fn exec_action(action_name: &str, player: &str, from_output_file: bool) -> Result<(), Box<dyn Error>> {
+    let player = if player.is_empty() && from_output_file {
+        get_output_file_player()
+    } else {
+        String::from(player)
+    };
+
+    // what the player allows, if it can be found
+    let metadata = find_player(&player).await;
+
+    // without the daemon, each scroll event is executed on its own
+    if let Some(step) = scroll::get_step(action_name) {
+        let settings = Settings::load()?;
+        let player_name = player.split('.').next().unwrap_or_default();
+        let mode = settings.mouse.get_scroll_mode(player_name);
+
+        return match scroll::get_playerctl_args(mode, step, &settings.mouse) {
+            Some(args) => {
+                if let Some(data) = &metadata {
+                    data.capabilities.resolve(&args[0], data.get_state_str())?;
+                }
+                exec_playerctl(&args, &player).await
+            }
+            None => Ok(()),
+        };
+    }
+
+    let command = match &metadata {
+        Some(data) => data.capabilities.resolve(action_name, data.get_state_str())?,
+        None => action_name,
+    };
+    exec_playerctl(&[String::from(command)], &player).await
+}
+
+/// Name of the player written in the state file, or in a legacy output file
+fn get_output_file_player() -> String {
+    [get_output_file_path(), get_legacy_output_file_path()]
+        .iter()
+        .filter(|v| !v.is_empty())
+        .find_map(|v| state_file::read_output_file_player(v).ok())
+        .unwrap_or_default()
+}
+
+/// Metadata of a player by instance or name, none if it is not running or the metadata command fails
+async fn find_player(player: &str) -> Option<PlayerMetadata> {
+    if player.is_empty() {
+        return None;
+    }
+
+    let players = fetch_list().await.ok()?;
+    let index = players.iter().position(|data| data.instance.eq(player))
+        .or_else(|| players.iter().position(|data| data.player.eq(player)))?;
+
+    players.into_iter().nth(index)
+}
+
+/// Executes playerctl with `args`, on `player` or the one chosen by playerctl
+async fn exec_playerctl(args: &[String], player: &str) -> Result<(), Box<dyn Error>> {
     let cmd_path = get_playerctl_cmd();
     let mut binding = Command::new(cmd_path);
-    let mut command = binding.arg(action_name);
+    let mut command = binding.args(args);
 
     if !player.is_empty() {
-        // get name of the player from argument
         command = command.arg("--player").arg(player);
-    } else if from_output_file {
-        // get name of the current player from output file
-        let output_file = get_output_file_path();
-        if !output_file.is_empty() {
-            let output_file_content = match read_first_line(&output_file) {
-                Ok(v) => v,
-                Err(_) => String::new()
-            };
-            if !output_file_content.is_empty() {
-                command = command.arg("--player").arg(output_file_content);
-            }
-        }
     }
 
-    let output = command.output()?;
+    let output = command.output().await?;
 
     // error if exit code is not 0
     if Some(0) != output.status.code() {
         // must return Err("")? (try! like) or Err("".into()) (less confusing)
-        return Err(String::from_utf8(output.stderr).unwrap().into());
+        return Err(String::from_utf8_lossy(&output.stderr).into());
     }
     Ok(())
 }
 
 async fn exec_list_action() -> Result<(), Box<dyn Error>> {
-    let data_list = fetch_list().await?;
-    let mut output = String::from("[");
-
-    for data in data_list.iter() {
-
-        let text = data.get_display();
-        let player_name = &data.player;
-
-        output.push_str("{");
-        // text
-        output.push_str("\"text\": ");
-        output.push_str((String::new() + "\"" + escape(&text).as_str() + "\"").as_str());
-        output.push_str(",");
-        // class
-        output.push_str(" \"class\": ");
-        output.push_str((String::new() + "\"custom-" + player_name.as_str() + "\"").as_str());
-        output.push_str(",");
-        // alt
-        output.push_str(" \"alt\": ");
-        output.push_str((String::new() + "\"" + player_name.as_str() + "\"").as_str());
-        output.push_str(",");
-
-        // instance
-        output.push_str(" \"instance\": ");
-        output.push_str((String::new() + "\"" + escape(&data.instance).as_str() + "\"").as_str());
-        output.push_str(",");
+    let data_list = apply_settings(fetch_list().await?, &Settings::load()?);
 
-        // state
-        output.push_str(" \"state\": ");
-        output.push_str((String::new() + "\"" + data.get_state_str() + "\"").as_str());
-        output.push_str(",");
+    println!("{}", format_list(&data_list));
 
-        // artist
-        output.push_str(" \"artist\": ");
-        output.push_str((String::new() + "\"" + escape(&data.artist).as_str() + "\"").as_str());
-        output.push_str(",");
-
-        // title
-        output.push_str(" \"title\": ");
-        output.push_str((String::new() + "\"" + escape(&data.title).as_str() + "\"").as_str());
-        output.push_str(",");
-
-        // album
-        output.push_str(" \"album\": ");
-        output.push_str((String::new() + "\"" + escape(&data.album).as_str() + "\"").as_str());
-        output.push_str(",");
-
-        // art_url
-        output.push_str(" \"art_url\": ");
-        output.push_str((String::new() + "\"" + escape(&data.art_url).as_str() + "\"").as_str());
-        //output.push_str(",");
+    Ok(())
+}
 
-        //// tooltip
-        //output.push_str(" \"tooltip\": ");
-        //output.push_str((String::new() + "\"" + text.as_str() + "\"").as_str());
+/// Prints the metadata of every player before and after the rules of the settings
+async fn exec_test_rules_action() -> Result<(), Box<dyn Error>> {
+    let settings = Settings::load()?;
+    let data_list = fetch_list().await?;
 
-        output.push_str("},");
+    if settings.rules.is_empty() {
+        println!("No rules in {}", settings::get_config_file_path());
     }
 
-    // remove the last comma
-    if data_list.len() > 0 {
-        output.pop();
+    for mut before in data_list {
+        // the rules see the split stream titles, as in the daemon
+        streams::apply(&settings.streams, &mut before);
+        let mut after = before.clone();
+        rules::apply(&settings.rules, &mut after);
+
+        println!("{}", before.instance);
+        for field in rules::RuleField::ALL.iter() {
+            let (value_before, value_after) = (field.get(&before), field.get(&after));
+            if value_before == value_after {
+                println!("  {:<7} {:?} (unchanged)", String::from(field.as_str()) + ":", value_before);
+            } else {
+                println!("  {:<7} {:?} -> {:?}", String::from(field.as_str()) + ":", value_before, value_after);
+            }
+        }
     }
 
-    output.push_str("]");
+    Ok(())
+}
 
-    println!("{}", output);
+/// Item of the JSON array of `list`
+#[derive(Serialize)]
+struct ListItem<'a> {
+    text: String,
+    class: String,
+    alt: &'a str,
+    instance: &'a str,
+    state: &'a str,
+    artist: &'a str,
+    title: &'a str,
+    album: &'a str,
+    art_url: &'a str,
+    station: &'a str,
+}
 
-    Ok(())
+/// Formats the metadata of the players as the JSON array of `list`
+pub fn format_list(data_list: &[PlayerMetadata]) -> String {
+    let items: Vec<ListItem> = data_list
+        .iter()
+        .map(|data| ListItem {
+            text: data.get_display(),
+            class: String::from("custom-") + &data.player,
+            alt: &data.player,
+            instance: &data.instance,
+            state: data.get_state_str(),
+            artist: &data.artist,
+            title: &data.title,
+            album: &data.album,
+            art_url: &data.art_url,
+            station: &data.station,
+        })
+        .collect();
+
+    // only strings, which always serialize
+    serde_json::to_string(&items).unwrap_or_else(|_| String::from("[]"))
 }
 
 /// Sends a command to the server or executes the action as a fallback.
 /// If action_name == "list", it returns a list of metadata.
-pub async fn send_action(action_name: &String, player: &String, no_server: bool, from_output_file: bool) -> Result<(), Box<dyn Error>> {
+pub async fn send_action(action_name: &str, player: &str, no_server: bool, from_output_file: bool) -> Result<(), Box<dyn Error>> {
     if action_name.eq("select") {
         if player.is_empty() {
             return Err("'select' command needs another argument (name of the player)".into());
         }
         // send message to select a player on the server
         let message: Vec<u8> = [action_name.as_bytes(), b" ", player.as_bytes()].concat();
-        send_message_to_server(message.as_slice())?;
+        send_message_to_server(message.as_slice()).await?;
     } else if action_name.eq("list") {
         exec_list_action().await?;
     } else {
         // try to send message to server
         if no_server {
-            exec_action(action_name, player, from_output_file)?;
+            exec_action(action_name, player, from_output_file).await?;
         } else {
             let message: Vec<u8> = [action_name.as_bytes(), b" ", player.as_bytes()].concat();
-            let result = send_message_to_server(message.as_slice());
+            let result = send_message_to_server(message.as_slice()).await;
 
             // fallback, execute the action
-            if let Err(_) = result {
-                exec_action(action_name, player, from_output_file)?;
+            if result.is_err() {
+                exec_action(action_name, player, from_output_file).await?;
             }
         }
         
@@ -477,15 +734,192 @@ pub async fn send_action(action_name: &String, player: &String, no_server: bool,
     Ok(())
 }
 
-fn parse_stream_message(mut stream: UnixStream) -> Option<StreamMessage> {
-    let mut buf = [0; 1024];
-    let count = std::io::Read::read(&mut stream, &mut buf).unwrap();
-    let response = String::from_utf8(buf[..count].to_vec()).unwrap();
+/// Reads the newline-terminated messages of a client until it disconnects.
+/// The last message may end without a newline.
+pub async fn read_stream_messages(
+    stream: UnixStream,
+    tx: &mpsc::Sender<StreamMessage>,
+    subscriptions: &Subscriptions,
+    limits: &ClientLimits,
+    permit: OwnedSemaphorePermit,
+) -> Result<(), Box<dyn Error>> {
+    let mut reader = BufReader::new(stream);
+
+    loop {
+        let mut line = String::new();
+
+        // fails on invalid UTF-8
+        let mut limited = (&mut reader).take(MAX_MESSAGE_LEN);
+        let count = match time::timeout(CLIENT_READ_TIMEOUT, limited.read_line(&mut line)).await {
+            Ok(result) => result?,
+            Err(_) => return Err("client timed out".into()),
+        };
+
+        if count == 0 {
+            // client disconnected
+            break;
+        }
+
+        if !line.ends_with('\n') && count as u64 == MAX_MESSAGE_LEN {
+            return Err(format!("message is longer than {} bytes", MAX_MESSAGE_LEN).into());
+        }
+
+        let mut message = StreamMessage::build(String::from(line.trim_end_matches(['\n', '\r'])))?;
+
+        if message.is_empty() {
+            continue;
+        }
+
+        if message.action.eq("subscribe") {
+            let Ok(_subscriber) = Arc::clone(&limits.subscribers).try_acquire_owned() else {
+                refuse(reader.get_ref());
+                return Err("too many subscribers, subscription refused".into());
+            };
+            // the client only reads from now on, and no longer counts among the clients
+            drop(permit);
+
+            // lines: the last one first, so the subscriber has something to show right away
+            if let Some(receiver) = subscriptions.get_latest(&message.player) {
+                return stream_latest(reader.into_inner(), receiver).await;
+            }
+            // events: only the new ones
+            if message.player == "events" {
+                return stream_lines(reader.into_inner(), subscriptions.events.subscribe()).await;
+            }
+            return Err(format!("unknown subscription '{}'", message.player).into());
+        }
+
+        if message.is_query() {
+            let (reply_tx, reply_rx) = oneshot::channel();
+            message.reply = Some(reply_tx);
+
+            // send info to the main loop and wait for its answer
+            tx.send(message).await?;
+            let mut response = reply_rx.await?;
+            response.push('\n');
+
+            reader.get_mut().write_all(response.as_bytes()).await?;
+        } else {
+            // send info to the main loop
+            tx.send(message).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Connections the server accepts at once (see `MAX_CLIENTS` and `MAX_SUBSCRIBERS`)
+#[derive(Clone)]
+pub struct ClientLimits {
+    clients: Arc<Semaphore>,
+    subscribers: Arc<Semaphore>,
+}
+
+impl Default for ClientLimits {
+    fn default() -> Self {
+        Self {
+            clients: Arc::new(Semaphore::new(MAX_CLIENTS)),
+            subscribers: Arc::new(Semaphore::new(MAX_SUBSCRIBERS)),
+        }
+    }
+}
+
+impl ClientLimits {
+    /// Slot of a new client, or None if there are too many already
+    pub fn try_acquire_client(&self) -> Option<OwnedSemaphorePermit> {
+        Arc::clone(&self.clients).try_acquire_owned().ok()
+    }
+}
+
+/// Tells a client its connection is refused, before it is closed
+fn refuse(stream: &UnixStream) {
+    // never waits, nothing else was written to the connection
+    let _ = stream.try_write((String::from(REFUSED_REPLY) + "\n").as_bytes());
+}
+
+/// Lines published by the daemon, that clients can subscribe to
+#[derive(Clone)]
+pub struct Subscriptions {
+    /// Events as JSON Lines (see `events::Event`)
+    events: broadcast::Sender<String>,
+    /// Last line printed on the standard output
+    display: Arc<watch::Sender<String>>,
+    /// Last line of each socket sink, by name
+    sinks: Arc<std::sync::Mutex<HashMap<String, Arc<watch::Sender<String>>>>>,
+    /// Last line of each button module (see `Button`)
+    buttons: Arc<[watch::Sender<String>; 3]>,
+}
+
+impl Default for Subscriptions {
+    fn default() -> Self {
+        Self {
+            events: broadcast::channel(SUBSCRIPTION_CAPACITY).0,
+            display: Arc::new(watch::channel(String::new()).0),
+            sinks: Arc::default(),
+            buttons: Arc::new(std::array::from_fn(|_| watch::channel(String::new()).0)),
+        }
+    }
+}
+
+impl Subscriptions {
+    /// Last line of `display`, a socket sink (`sink:<name>`) or a button module (`button:<name>`)
+    fn get_latest(&self, name: &str) -> Option<watch::Receiver<String>> {
+        if name == "display" {
+            return Some(self.display.subscribe());
+        }
+        if let Some(name) = name.strip_prefix("sink:") {
+            return Some(self.sinks.lock().ok()?.get(name)?.subscribe());
+        }
+
+        let button = Button::from_str(name.strip_prefix("button:")?, true).ok()?;
+        Some(self.buttons[button as usize].subscribe())
+    }
+
+    /// Channel of a socket sink, created on first use so subscribers stay across reloads
+    pub fn get_sink(&self, name: &str) -> Arc<watch::Sender<String>> {
+        let mut sinks = self.sinks.lock().unwrap_or_else(|err| err.into_inner());
+        sinks.entry(String::from(name)).or_insert_with(|| Arc::new(watch::channel(String::new()).0)).clone()
+    }
+}
+
+/// Writes the last line to a subscriber, then every new one until it disconnects
+async fn stream_latest(mut stream: UnixStream, mut receiver: watch::Receiver<String>) -> Result<(), Box<dyn Error>> {
+    loop {
+        let mut line = receiver.borrow_and_update().clone();
+        line.push('\n');
+
+        if stream.write_all(line.as_bytes()).await.is_err() {
+            // subscriber disconnected
+            break;
+        }
+        if receiver.changed().await.is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the published lines to a subscriber until it disconnects
+async fn stream_lines(mut stream: UnixStream, mut receiver: broadcast::Receiver<String>) -> Result<(), Box<dyn Error>> {
+    loop {
+        let mut line = match receiver.recv().await {
+            Ok(v) => v,
+            Err(broadcast::error::RecvError::Lagged(count)) => {
+                eprintln!("Subscriber too slow, {} lines skipped", count);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        line.push('\n');
 
-    match StreamMessage::build(response) {
-        Ok(result) => Some(result),
-        _ => None
+        if stream.write_all(line.as_bytes()).await.is_err() {
+            // subscriber disconnected
+            break;
+        }
     }
+
+    Ok(())
 }
 
 fn get_first_line<R>(mut rdr: R) -> Result<String, Box<dyn Error>>
@@ -499,7 +933,7 @@ fn get_first_line<R>(mut rdr: R) -> Result<String, Box<dyn Error>>
     Ok(first_line)
 }
 
-pub fn read_first_line(file_path: &String) -> Result<String, Box<dyn Error>> {
+pub fn read_first_line(file_path: &str) -> Result<String, Box<dyn Error>> {
     let file = fs::File::open(file_path)?;
     let buffer = std::io::BufReader::new(file)
         .take(256); // limit number of bytes to be read before returning EOF
@@ -515,265 +949,766 @@ pub fn read_first_line(file_path: &String) -> Result<String, Box<dyn Error>> {
     )
 }
 
-fn write_to_file(file_path: &String, content: &String) -> Result<(), Box<dyn Error>> {
-    fs::write(file_path, content)?;
+/// Writes into a temporary file renamed over `file_path`, so readers never see a partial file.
+/// Creates the parent directories if needed.
+pub(crate) fn write_to_file_atomically(file_path: &str, content: &str) -> Result<(), Box<dyn Error>> {
+    let path = std::path::Path::new(file_path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let tmp_path = format!("{}.{}.tmp", file_path, std::process::id());
+    fs::write(&tmp_path, content)?;
+
+    if let Err(err) = fs::rename(&tmp_path, path) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(err.into());
+    }
+
     Ok(())
 }
 
-fn start_server(tx: std::sync::mpsc::Sender<StreamMessage>, no_server: bool, force_clean_start: bool) -> JoinHandle<()> {
-    thread::spawn(move || {
-        if no_server {
-            // end thread here
-            return;
+/// Socket server listening for actions in its own task
+struct Server {
+    handle: JoinHandle<()>,
+    /// false if the socket belongs to someone else (e.g.: systemd)
+    bound: bool,
+}
+
+impl Server {
+    /// Stops accepting clients and removes the socket if it created it
+    fn stop(self) {
+        self.handle.abort();
+
+        if self.bound {
+            if let Err(err) = std::fs::remove_file(SOCK_PATH) {
+                eprintln!("Could not remove socket: {err}");
+            }
         }
+    }
+}
 
-        if force_clean_start {
-            let _ = std::fs::remove_file(SOCK_PATH);
+fn start_server(tx: mpsc::Sender<StreamMessage>, subscriptions: Subscriptions, no_server: bool, force_clean_start: bool) -> Server {
+    if no_server {
+        return Server { handle: tokio::spawn(async {}), bound: false };
+    }
+
+    let (listener, bound) = match listen(force_clean_start) {
+        Ok(v) => v,
+        Err(err) => {
+            eprintln!("Got listener error: {err:?}");
+            return Server { handle: tokio::spawn(async {}), bound: false };
         }
+    };
 
-        // listen to Unix socket (https://doc.rust-lang.org/std/os/unix/net/struct.UnixListener.html)
-        let listener = match UnixListener::bind(SOCK_PATH) {
-            Ok(sock) => {
-                if let Ok(Some(err)) = sock.take_error() {
-                    eprintln!("Got listener error: {err:?}");
-                }
-                Some(sock)
-            }
-            err => {
-                eprintln!("Got listener error: {err:?}");
-                None
-            },
-        };
-    
-        if let Some(sock) = listener {
-            // listen to incoming streams (clients)
-            for stream in sock.incoming() {
-                //println!("incoming stream");
-                match stream {
-                    Ok(stream) => {
-                        let message = parse_stream_message(stream);
-                        
-                        if let Some(stream_message) = message {
-                            // do something
-                            if stream_message.is_empty() {
-                                // message is empty so we are done here
-                                break;
-                            }
-                            // send info to main thread
-                            let _ = tx.send(stream_message);
-                        } else {
-                            // we are done here
-                            break;
+    let handle = tokio::spawn(async move {
+        let limits = ClientLimits::default();
+
+        // listen to incoming streams (clients)
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let permit = match limits.try_acquire_client() {
+                        Some(v) => v,
+                        None => {
+                            eprintln!("Too many clients, connection refused");
+                            refuse(&stream);
+                            continue;
                         }
-                    }
-                    Err(err) => {
-                        eprintln!("Got socket error: {err:?}");
-                        break;
-                    }
+                    };
+
+                    // each client in its own task so a slow one does not block the others
+                    let tx = tx.clone();
+                    let subscriptions = subscriptions.clone();
+                    let limits = limits.clone();
+                    tokio::spawn(async move {
+                        if let Err(err) = read_stream_messages(stream, &tx, &subscriptions, &limits, permit).await {
+                            eprintln!("Got client error: {err}");
+                        }
+                    });
+                }
+                Err(err) => {
+                    eprintln!("Got socket error: {err:?}");
+                    break;
                 }
             }
         }
-        
-        std::fs::remove_file(SOCK_PATH).unwrap();
-    })
+    });
+
+    Server { handle, bound }
+}
+
+/// Listens to the socket passed by systemd (socket activation) or binds a new one.
+/// The boolean is true if the socket was created here.
+fn listen(force_clean_start: bool) -> Result<(UnixListener, bool), Box<dyn Error>> {
+    match systemd::take_listener() {
+        Ok(Some(sock)) => return Ok((UnixListener::from_std(sock)?, false)),
+        Ok(None) => {}
+        Err(err) => eprintln!("Could not use the socket passed by systemd: {err}"),
+    }
+
+    if force_clean_start {
+        let _ = std::fs::remove_file(SOCK_PATH);
+    }
+
+    Ok((UnixListener::bind(SOCK_PATH)?, true))
 }
 
-async fn fetch_info(current_player: &String) -> Result<InfoResponse, Box<dyn Error>> {
+async fn fetch_info(current_instance: &str, current_player: &str, settings: &Settings) -> Result<InfoResponse, Box<dyn Error>> {
     let mut new_player = String::new();
-    let mut new_state = String::new();
     let mut new_instance = String::new();
-    //let mut art_url = String::new();
-    //let mut artist = String::new();
-    //let mut album = String::new();
-    //let mut title = String::new();
-    
+
     // fetch data
-    let (code, metadata, text) = fetch_data(current_player).await?;
+    let (code, metadata, text, players) = fetch_data(current_instance, current_player, settings).await?;
 
     // something happened while trying to fetch data
     if let Some(v) = code {
         if v != 0 {
-            return Ok(InfoResponse { code: v, player: new_player, display: text, state: new_state, instance: new_instance
-                //art_url, album, artist, title 
-            });
+            return Ok(InfoResponse { code: v, player: new_player, display: text, instance: new_instance, metadata: None, players });
         }
     }
 
     // player to display/control
-    if let Some(value) = metadata {
-        new_state = String::from(value.get_state_str());
-        new_player = value.player;
-        new_instance = value.instance;
-        //art_url = value.art_url;
-        //album = value.album;
-        //artist = value.artist;
-        //title = value.title;
-    }
-    
-    Ok(InfoResponse { code: 0, player: new_player, display: text, state: new_state, instance: new_instance
-        //art_url, album, artist, title 
-    })
-}
-
-///
-/// Prints json element or empty string if first argument is empty
-fn print_one_json_element(text: &String, player: &String, state: &String, instance: &String) {
-    if text.is_empty() {
-        println!("{}", text);
-    } else {
-        println!(
-            "{{\"text\": \"{}\", \"class\": [\"custom-{}\", \"{}\"], \"alt\": \"{}\", \"tooltip\": \"({}) {}\", \"state\": \"{}\", \"instance\": \"{}\"}}",
-            escape(&text), player, state.to_lowercase(), player, player, escape_ampersand(&escape(&text)), state.to_lowercase(), instance
-        );
+    if let Some(value) = &metadata {
+        new_player = value.player.clone();
+        new_instance = value.instance.clone();
     }
+
+    Ok(InfoResponse { code: 0, player: new_player, display: text, instance: new_instance, metadata, players })
+}
+
+/// Prints the script registering the completions of `shell`.
+/// The script calls back into this binary so player names are completed live.
+fn print_completions(shell: cli::Shell) -> Result<(), Box<dyn Error>> {
+    let shells = Shells::builtins();
+    let completer = match shells.completer(shell.as_str()) {
+        Some(v) => v,
+        None => return Err(format!("no completer for {}", shell.as_str()).into()),
+    };
+
+    let cmd = Cli::command();
+    let name = cmd.get_name();
+    let bin = env::current_exe()?;
+
+    completer.write_registration("COMPLETE", name, name, &bin.to_string_lossy(), &mut std::io::stdout())?;
+
+    Ok(())
 }
 
 pub async fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    if !config.action.is_empty() {
-        // do action
-        send_action(&config.action, &config.player, config.no_server, config.from_output_file).await?;
-    } else {
-        let ctrl_c_events = ctrl_channel()?;
-        let refresh_ticks = tick(Duration::from_secs(1));
-        let stream_listener_ticks = tick(Duration::from_millis(300));
+    match config.command {
+        Commands::Daemon(args) => run_daemon(args).await?,
+        Commands::Action(args) => {
+            let player = args.player.unwrap_or_default();
+            send_action(args.action.as_str(), &player, args.no_server, args.from_output_file).await?;
+        }
+        Commands::Select { player } => {
+            send_action("select", &player, false, false).await?;
+        }
+        Commands::List => {
+            send_action("list", "", false, false).await?;
+        }
+        Commands::TestRules => exec_test_rules_action().await?,
+        Commands::Status { format } => exec_status_action(format.as_deref()).await?,
+        Commands::Print { format } => exec_print_action(format).await?,
+        Commands::Watch { events, sink, button } => {
+            let subscription = match (sink, button) {
+                (Some(name), _) => format!("sink:{}", name),
+                (None, Some(button)) => format!("button:{}", button.as_str()),
+                (None, None) if events => String::from("events"),
+                (None, None) => String::from("display"),
+            };
+            exec_watch_action(&subscription).await?
+        }
+        Commands::Completions { shell } => print_completions(shell)?,
+    }
 
-        let mut current_display = String::new();
-        let mut current_player: String = String::new();
-        let mut current_instance: String = String::new();
+    Ok(())
+}
 
-        let (tx, rx): (std::sync::mpsc::Sender<StreamMessage>, std::sync::mpsc::Receiver<StreamMessage>) = std::sync::mpsc::channel();
+/// What the daemon is currently displaying
+struct Daemon {
+    config: DaemonArgs,
+    settings: Settings,
+    current_display: String,
+    /// Last line printed
+    current_line: String,
+    current_player: String,
+    current_instance: String,
+    current_metadata: Option<PlayerMetadata>,
+    /// When `current_metadata` was fetched
+    fetched_at: Instant,
+    /// How the current player was chosen (see `Status::selection`)
+    selection: &'static str,
+    started_at: Instant,
+    /// Every player at the last fetch, none before the first one
+    players: Option<Vec<PlayerMetadata>>,
+    hooks: Hooks,
+    subscriptions: Subscriptions,
+    /// Scroll events waiting to be executed as one call
+    scroll: Option<PendingScroll>,
+    /// One for each sink of the settings
+    sinks: Vec<SinkState>,
+    /// Last content of the output file
+    state_file: Option<StateFile>,
+    /// Players paused by exclusive playback, to resume
+    playback: PlaybackStack,
+}
 
-        let handle = start_server(tx, config.no_server, config.force_clean_start);
+impl Daemon {
+    /// Fetches the data of the current player, prints it if it changed (or if `force`),
+    /// and updates the output file if the player changed (or if `force`).
+    /// Returns false if the data could not be fetched.
+    async fn update(&mut self, force: bool) -> Result<bool, Box<dyn Error>> {
+        let mut it_should_update_output_file = force;
 
-        loop {
-            select! {
-                recv(stream_listener_ticks) -> _ => {
-                    let mut received_message = false;
-                    
-                    // check received stream messages
-                    match rx.try_recv() {
-                        Ok(message) => {
-                            if message.action.eq("select") {
-                                // changing player
-                                current_player = String::from(&message.player);
-                                current_instance = message.player;
-                                received_message = true;
-                            } else {
-                                let result = exec_action(&message.action, &current_instance, false);
-                                if let Err(err) = result {
-                                    eprintln!("Error (exec_action): {err:?}");
-                                } else {
-                                    received_message = true;
-                                }
-                            }
-                        }
-                        Err(_) => {}
-                    }
+        let info = fetch_info(&self.current_instance, &self.current_player, &self.settings).await?;
 
-                    if received_message {
+        if info.code != 0 {
+            return Ok(false);
+        }
 
-                        let info = fetch_info(&current_instance).await?;
+        // the selected player is gone, fell back to the first one
+        if !self.current_instance.eq(&info.instance) && !self.current_player.eq(&info.player) {
+            self.selection = "first";
+        }
+        let previous_metadata = std::mem::replace(&mut self.current_metadata, info.metadata);
+        let elapsed = self.fetched_at.elapsed();
+        self.fetched_at = Instant::now();
+
+        // what changed since the last fetch
+        let mut changes = vec![];
+        if let Some(previous) = &self.players {
+            changes = events::diff(previous, &info.players, elapsed);
+
+            // the displayed player changed
+            if let Some(data) = &self.current_metadata {
+                if previous_metadata.as_ref().map(|v| &v.instance) != Some(&data.instance) {
+                    changes.push(Event::create(EventKind::SelectionChanged, data, previous_metadata.as_ref()));
+                }
+            }
 
-                        if info.code != 0 {
-                            break;
-                        }
+            for event in changes.iter() {
+                self.emit(event);
+            }
+        }
+        self.players = Some(info.players);
 
-                        // text to display
-                        if !current_display.eq(&info.display) {
-                            current_display = info.display;
-                        }
+        // text to display
+        self.current_display = info.display;
 
-                        if !current_player.eq(&info.player) {
-                            current_player = info.player;
-                        }
+        if !self.current_player.eq(&info.player) {
+            self.current_player = info.player;
+            it_should_update_output_file = true;
+        }
 
-                        if !current_instance.eq(&info.instance) {
-                            current_instance = info.instance;
-                        }
+        if !self.current_instance.eq(&info.instance) {
+            self.current_instance = info.instance;
+            it_should_update_output_file = true;
+        }
 
-                        // print
-                        print_one_json_element(&current_display, &current_player, &info.state, &current_instance);
+        // print if the line changed
+        let line = self.format_line(self.config.format);
 
-                        if config.from_output_file {
-                            let output_file = get_output_file_path();
-                            if !output_file.is_empty() {
-                                // write name of player into the file
-                                if let Err(err) = write_to_file(&output_file, &current_player) {
-                                    eprintln!("write_to_file error: {} => {}", output_file, err);
-                                }
-                            }
-                        }
-                    }
+        if force || !self.current_line.eq(&line) {
+            println!("{}", line);
+            self.subscriptions.display.send_replace(line.clone());
+            self.current_line = line;
+        }
+
+        for button in Button::ALL {
+            let line = self.config.format.format_button(button, &self.get_element(), &self.settings);
+            self.subscriptions.buttons[button as usize].send_if_modified(|value| {
+                if *value == line {
+                    return false;
                 }
-                recv(refresh_ticks) -> _ => {
-                    let mut it_should_print = false;
-                    let mut it_should_update_output_file = false;
+                *value = line;
+                true
+            });
+        }
 
-                    let info = fetch_info(&current_instance).await?;
+        self.write_sinks(force).await;
 
-                    if info.code != 0 {
-                        break;
-                    }
+        if self.config.from_output_file {
+            self.write_output_file(it_should_update_output_file);
+        }
 
-                    // text to display
-                    if !current_display.eq(&info.display) {
-                        current_display = info.display;
-                        it_should_print = true;
-                    }
+        if self.settings.playback.exclusive {
+            self.pause_interrupted(&changes).await;
+            // displays the resumed players, their new state and the selection now, instead of on the next fetch
+            if self.settings.playback.resume && self.resume_interrupted(&changes).await {
+                return Box::pin(self.update(true)).await;
+            }
+        }
 
-                    if !current_player.eq(&info.player) {
-                        current_player = info.player;
-                        it_should_print = true;
-                        it_should_update_output_file = true;
-                    }
+        Ok(true)
+    }
 
-                    if !current_instance.eq(&info.instance) {
-                        current_instance = info.instance;
-                        it_should_print = true;
-                        it_should_update_output_file = true;
-                    }
+    /// Pauses the players that were playing before another one started
+    async fn pause_interrupted(&mut self, changes: &[Event]) {
+        let players = self.players.as_deref().unwrap_or_default();
+        let Some(started) = playback::get_started(&self.settings.playback, changes) else {
+            return;
+        };
 
-                    // print
-                    if it_should_print {
-                        print_one_json_element(&current_display, &current_player, &info.state, &current_instance);
-                    }
+        for data in playback::get_interrupted(&self.settings.playback, changes, players) {
+            let Ok(command) = data.capabilities.resolve("pause", data.get_state_str()) else {
+                continue;
+            };
+            if let Err(err) = exec_playerctl(&[String::from(command)], &data.instance).await {
+                eprintln!("Could not pause {}: {err}", data.instance);
+                continue;
+            }
 
-                    if it_should_update_output_file && config.from_output_file {
-                        let output_file = get_output_file_path();
-                        if !output_file.is_empty() {
-                            // write name of player into the file
-                            if let Err(err) = write_to_file(&output_file, &current_player) {
-                                eprintln!("write_to_file error: {} => {}", output_file, err);
-                            }
-                        }
+            self.playback.push(Interruption {
+                paused: data.instance.clone(),
+                by: started.instance.clone(),
+                selected_player: self.current_player.clone(),
+                selected_instance: self.current_instance.clone(),
+                selection: self.selection,
+            });
+        }
+    }
+
+    /// Plays again the players paused by `pause_interrupted` whose interruption ended,
+    /// and displays them again if the player that interrupted them was displayed.
+    /// Returns true if a player was resumed.
+    async fn resume_interrupted(&mut self, changes: &[Event]) -> bool {
+        let players = self.players.as_deref().unwrap_or_default();
+        let mut resumed = false;
+
+        for interruption in self.playback.take_ended(&self.settings.playback, changes, players) {
+            let Some(data) = players.iter().find(|v| v.instance == interruption.paused) else {
+                continue;
+            };
+            let Ok(command) = data.capabilities.resolve("play", data.get_state_str()) else {
+                continue;
+            };
+            if let Err(err) = exec_playerctl(&[String::from(command)], &data.instance).await {
+                eprintln!("Could not resume {}: {err}", data.instance);
+                continue;
+            }
+            resumed = true;
+
+            let interrupter_gone = !players.iter().any(|v| v.instance == interruption.by);
+            if interrupter_gone || self.current_instance == interruption.by {
+                // the interrupter may already have been displayed when the player was paused
+                if interruption.selected_instance == interruption.by {
+                    self.current_player = data.player.clone();
+                    self.current_instance = data.instance.clone();
+                } else {
+                    self.current_player = interruption.selected_player;
+                    self.current_instance = interruption.selected_instance;
+                }
+                self.selection = interruption.selection;
+            }
+        }
+
+        resumed
+    }
+
+    /// Handles a message received by the server.
+    /// Returns false if the data could not be fetched afterwards.
+    async fn handle_message(&mut self, message: StreamMessage) -> Result<bool, Box<dyn Error>> {
+        if message.action.eq("status") {
+            if let Some(reply) = message.reply {
+                let _ = reply.send(serde_json::to_string(&self.get_status())?);
+            }
+            return Ok(true);
+        } else if message.action.eq("print") {
+            if let Some(reply) = message.reply {
+                let format = match message.player.as_str() {
+                    "" => Ok(self.config.format),
+                    name => OutputFormat::from_str(name, true),
+                };
+                let line = match format {
+                    Ok(v) => self.format_line(v),
+                    Err(err) => {
+                        eprintln!("Invalid format to print: {err}");
+                        String::new()
                     }
+                };
+                let _ = reply.send(line);
+            }
+            return Ok(true);
+        } else if message.action.eq("select") {
+            // changing player
+            self.current_player = String::from(&message.player);
+            self.current_instance = message.player;
+            self.selection = "selected";
+
+            let result = self.update(true).await;
+
+            // remember the player if it exists
+            if self.selection == "selected" {
+                self.save_selection();
+            }
+
+            return result;
+        } else if let Some(step) = scroll::get_step(&message.action) {
+            // a burst over another player ends the previous one
+            if self.scroll.as_ref().is_some_and(|v| v.player != message.player) {
+                self.flush_scroll().await?;
+            }
+            // added up until the end of the burst
+            let debounce = Duration::from_millis(self.settings.mouse.debounce);
+            let pending = self.scroll.get_or_insert_with(|| PendingScroll { player: message.player, steps: 0, until: Instant::now() + debounce });
+            pending.steps += step;
+            return Ok(true);
+        }
+
+        let args = match self.resolve(&message.action, &message.player) {
+            Ok(command) => [String::from(command)],
+            Err(err) => {
+                eprintln!("Error (exec_action): {err}");
+                return Ok(true);
+            }
+        };
+        if let Err(err) = exec_playerctl(&args, self.get_target(&message.player)).await {
+            eprintln!("Error (exec_action): {err:?}");
+            return Ok(true);
+        }
+
+        self.update(true).await
+    }
+
+    /// Writes the current player into the output file, as JSON if it changed,
+    /// or its name on one line if `player_changed` (legacy)
+    fn write_output_file(&mut self, player_changed: bool) {
+        let (output_file, content) = if self.config.legacy_output_file {
+            if !player_changed {
+                return;
+            }
+            (get_legacy_output_file_path(), self.current_player.clone())
+        } else {
+            let state_file = StateFile::create(self.current_metadata.as_ref());
+            if !player_changed && self.state_file.as_ref().is_some_and(|v| v.is_same(&state_file)) {
+                return;
+            }
+            let content = match serde_json::to_string(&state_file) {
+                Ok(v) => v + "\n",
+                Err(err) => {
+                    eprintln!("Could not serialize the state file: {err}");
+                    return;
                 }
-                recv(ctrl_c_events) -> _ => {
-                    // quit
-
-                    // cleanup default output
-                    println!();
-                    
-                    // clean up output file
-                    let output_file = get_output_file_path();
-                    if !output_file.is_empty() && config.from_output_file {
-                        if let Err(err) = write_to_file(&output_file, &String::new()) {
-                            eprintln!("write_to_file error: {} => {}", output_file, err);
-                        }
+            };
+            self.state_file = Some(state_file);
+            (get_output_file_path(), content)
+        };
+
+        if output_file.is_empty() {
+            return;
+        }
+        if let Err(err) = write_to_file_atomically(&output_file, &content) {
+            eprintln!("write_to_file error: {} => {}", output_file, err);
+        }
+    }
+
+    /// Writes the sinks whose filter matches what changed
+    async fn write_sinks(&mut self, force: bool) {
+        self.sinks.resize_with(self.settings.sinks.len(), SinkState::default);
+
+        for (index, sink) in self.settings.sinks.iter().enumerate() {
+            let content = match (&sink.format, &sink.template) {
+                (Some(format), _) => self.format_line(*format),
+                (None, Some(template)) => match self.get_status().format(template) {
+                    Ok(v) => v,
+                    Err(err) => {
+                        eprintln!("Sink error: {err}");
+                        continue;
                     }
+                },
+                (None, None) => continue,
+            };
 
-                    break;
+            let data = self.current_metadata.as_ref();
+            let key = match sink.filter {
+                SinkFilter::Content => content.clone(),
+                SinkFilter::Track => data.map(|v| [v.instance.as_str(), &v.artist, &v.title, &v.album].join("\n")).unwrap_or_default(),
+                SinkFilter::State => data.map(|v| [v.instance.as_str(), v.get_state_str()].join("\n")).unwrap_or_default(),
+                SinkFilter::Player => self.current_instance.clone(),
+            };
+
+            let Some(content) = self.sinks[index].take(sink, key, content, force) else {
+                continue;
+            };
+
+            let subscribers = match sink.kind {
+                SinkKind::Socket => Some(self.subscriptions.get_sink(sink.name.as_deref().unwrap_or_default())),
+                _ => None,
+            };
+            if let Err(err) = sinks::write(sink, &content, subscribers.as_deref()).await {
+                eprintln!("Sink error: {err}");
+            }
+        }
+    }
+
+    /// What is displayed for the current player
+    fn get_element(&self) -> Element<'_> {
+        Element {
+            text: &self.current_display,
+            player: &self.current_player,
+            state: self.current_metadata.as_ref().map(|v| v.get_state_str()).unwrap_or_default(),
+            instance: &self.current_instance,
+            metadata: self.current_metadata.as_ref(),
+            players: self.players.as_deref().unwrap_or_default(),
+        }
+    }
+
+    /// Formats the current player, or every player, for a status bar
+    fn format_line(&self, format: OutputFormat) -> String {
+        if !self.config.all_players {
+            return format.format(&self.get_element(), &self.settings);
+        }
+
+        let players = self.players.as_deref().unwrap_or_default();
+        let mut order: Vec<&PlayerMetadata> = players.iter().collect();
+        match self.settings.all_players.order {
+            PlayerOrder::Listed => {}
+            // in the order of the variants: playing, paused, stopped
+            PlayerOrder::PlayingFirst => order.sort_by_key(|v| v.state as u8),
+            PlayerOrder::SelectedFirst => order.sort_by_key(|v| v.instance != self.current_instance),
+            PlayerOrder::Name => order.sort_by(|a, b| (&a.player, &a.instance).cmp(&(&b.player, &b.instance))),
+        }
+
+        let displays: Vec<String> = order.iter().map(|v| v.get_display()).collect();
+        let elements: Vec<Element> = order
+            .iter()
+            .zip(displays.iter())
+            .map(|(data, display)| Element {
+                text: display,
+                player: &data.player,
+                state: data.get_state_str(),
+                instance: &data.instance,
+                metadata: Some(data),
+                players,
+            })
+            .collect();
+        let selected = order.iter().position(|v| v.instance == self.current_instance).unwrap_or_default();
+
+        format.format_all(&elements, selected, &self.settings)
+    }
+
+    /// Instance or name of the player an action targets, the one displayed if `player` is empty
+    fn get_target<'a>(&'a self, player: &'a str) -> &'a str {
+        if player.is_empty() {
+            &self.current_instance
+        } else {
+            player
+        }
+    }
+
+    /// Metadata of the player an action targets (see `get_target`)
+    fn find_player(&self, player: &str) -> Option<&PlayerMetadata> {
+        if player.is_empty() {
+            return self.current_metadata.as_ref();
+        }
+        let players = self.players.as_deref().unwrap_or_default();
+        players.iter().find(|v| v.instance == player).or_else(|| players.iter().find(|v| v.player == player))
+    }
+
+    /// Playerctl command to execute for `command`, as allowed by the targeted player
+    fn resolve<'a>(&self, command: &'a str, player: &str) -> Result<&'a str, Box<dyn Error>> {
+        match self.find_player(player) {
+            Some(data) => data.capabilities.resolve(command, data.get_state_str()),
+            None => Ok(command),
+        }
+    }
+
+    /// Executes the pending scroll events as one call
+    async fn flush_scroll(&mut self) -> Result<bool, Box<dyn Error>> {
+        let Some(pending) = self.scroll.take() else {
+            return Ok(true);
+        };
+
+        let player_name = self.find_player(&pending.player).map(|v| v.player.as_str()).unwrap_or_default();
+        let mode = self.settings.mouse.get_scroll_mode(player_name);
+
+        let Some(args) = scroll::get_playerctl_args(mode, pending.steps, &self.settings.mouse) else {
+            return Ok(true);
+        };
+        if let Err(err) = self.resolve(&args[0], &pending.player) {
+            eprintln!("Error (exec_playerctl): {err}");
+            return Ok(true);
+        }
+        if let Err(err) = exec_playerctl(&args, self.get_target(&pending.player)).await {
+            eprintln!("Error (exec_playerctl): {err:?}");
+            return Ok(true);
+        }
+
+        self.update(true).await
+    }
+
+    /// Runs the hook of the event and sends it to the subscribers
+    fn emit(&self, event: &Event) {
+        self.hooks.run(&self.settings.hooks, event);
+
+        if self.subscriptions.events.receiver_count() > 0 {
+            match serde_json::to_string(event) {
+                Ok(line) => {
+                    let _ = self.subscriptions.events.send(line);
+                }
+                Err(err) => eprintln!("Could not serialize event: {err}"),
+            }
+        }
+    }
+
+    /// Saves the current player so it is selected again when the daemon restarts
+    fn save_selection(&self) {
+        let saved = SavedSelection {
+            player: self.current_player.clone(),
+            instance: self.current_instance.clone(),
+        };
+
+        if let Err(err) = saved.save() {
+            eprintln!("Could not save the selected player: {err}");
+        }
+    }
+
+    fn get_status(&self) -> Status {
+        let metadata = self.current_metadata.as_ref();
+
+        // the position keeps moving between two fetches
+        let position = metadata.and_then(|data| {
+            let position = match data.state {
+                State::Playing => data.position? + self.fetched_at.elapsed().as_micros() as u64,
+                _ => data.position?,
+            };
+            Some(data.length.map_or(position, |length| position.min(length)))
+        });
+
+        Status::create(metadata, position, self.selection, self.started_at.elapsed().as_secs(), &self.settings.icons)
+    }
+
+    /// Clears the outputs before quitting
+    fn clean_up(&self) {
+        let _ = systemd::notify("STOPPING=1");
+
+        // cleanup default output
+        println!("{}", self.config.format.format_empty());
+
+        // clean up output file
+        if self.config.from_output_file {
+            let (output_file, content) = if self.config.legacy_output_file {
+                (get_legacy_output_file_path(), String::new())
+            } else {
+                (get_output_file_path(), serde_json::to_string(&StateFile::create(None)).unwrap_or_default() + "\n")
+            };
+            if !output_file.is_empty() {
+                if let Err(err) = write_to_file_atomically(&output_file, &content) {
+                    eprintln!("write_to_file error: {} => {}", output_file, err);
                 }
             }
         }
+    }
+}
+
+async fn run_daemon(config: DaemonArgs) -> Result<(), Box<dyn Error>> {
+    let settings = Settings::load()?;
+
+    let mut interrupt_events = signal(SignalKind::interrupt())?;
+    let mut terminate_events = signal(SignalKind::terminate())?;
+    let mut reload_events = signal(SignalKind::hangup())?;
+    let mut refetch_events = signal(SignalKind::user_defined1())?;
+
+    let mut refresh_ticks = time::interval(Duration::from_millis(settings.interval));
+
+    let (tx, mut rx) = mpsc::channel::<StreamMessage>(32);
+
+    let subscriptions = Subscriptions::default();
+
+    if let Some(header) = config.format.header() {
+        println!("{}", header);
+    }
+
+    // clicks on the status bar come as actions
+    if config.format.has_click_events() {
+        output::read_click_events(tx.clone());
+    }
+
+    let server = start_server(tx, subscriptions.clone(), config.no_server, config.force_clean_start);
+
+    // tell systemd the daemon is ready (Type=notify)
+    if let Err(err) = systemd::notify("READY=1") {
+        eprintln!("Could not notify systemd: {err}");
+    }
 
-        if !config.no_server {
-            // send message to stop server
-            send_message_to_server(b" ")?;
+    // select the player selected before the last restart
+    let (current_player, current_instance, selection) = match SavedSelection::load() {
+        Ok(Some(saved)) => (saved.player, saved.instance, "restored"),
+        Ok(None) => (String::new(), String::new(), "first"),
+        Err(err) => {
+            eprintln!("Could not restore the selected player: {err}");
+            (String::new(), String::new(), "first")
         }
+    };
+
+    let mut daemon = Daemon {
+        config,
+        settings,
+        current_display: String::new(),
+        current_line: String::new(),
+        current_player,
+        current_instance,
+        current_metadata: None,
+        fetched_at: Instant::now(),
+        selection,
+        started_at: Instant::now(),
+        players: None,
+        hooks: Hooks::default(),
+        subscriptions,
+        scroll: None,
+        sinks: vec![],
+        state_file: None,
+        playback: PlaybackStack::default(),
+    };
+
+    loop {
+        let next = async {
+            tokio::select! {
+                // handle the message as soon as it is received, or after the first fetch:
+                // without a current player yet, an action would go to any player (e.g.: a click at startup)
+                Some(message) = rx.recv(), if daemon.players.is_some() => daemon.handle_message(message).await,
+                _ = refresh_ticks.tick() => daemon.update(false).await,
+                _ = time::sleep_until(daemon.scroll.as_ref().map_or_else(Instant::now, |v| v.until)), if daemon.scroll.is_some() => {
+                    daemon.flush_scroll().await
+                }
+                _ = refetch_events.recv() => daemon.update(true).await,
+                _ = reload_events.recv() => {
+                    match Settings::load() {
+                        Ok(settings) => {
+                            if settings.interval != daemon.settings.interval {
+                                refresh_ticks = time::interval(Duration::from_millis(settings.interval));
+                            }
+                            daemon.settings = settings;
+                        }
+                        Err(err) => eprintln!("Could not reload configuration, keeping the previous one: {err}"),
+                    }
+                    daemon.update(true).await
+                }
+            }
+        };
+
+        // the signals also end an update, e.g. one waiting for a metadata command that hangs
+        let result = tokio::select! {
+            result = next => result,
+            _ = interrupt_events.recv() => Ok(false),
+            _ = terminate_events.recv() => Ok(false),
+        };
 
-        handle.join().unwrap();
+        match result {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(err) => {
+                daemon.clean_up();
+                server.stop();
+                return Err(err);
+            }
+        }
     }
 
+    daemon.clean_up();
+    server.stop();
+
     Ok(())
 }
@@ -1,11 +1,16 @@
-use mpris_widget::Config;
+use clap::CommandFactory;
+use clap_complete::CompleteEnv;
+use mpris_widget::{cli::Cli, Config};
 use std::{env, process};
 
 #[tokio::main] // to allow 'main' function to be async
 async fn main() {
+    // answer the shell if it is asking for completions
+    CompleteEnv::with_factory(Cli::command).complete();
+
     let config = Config::build(env::args()).unwrap_or_else(|err| {
-        eprintln!("Problem parsing arguments: {err}");
-        process::exit(1);
+        // prints help, version or the parsing error
+        err.exit();
     });
 
     // run application
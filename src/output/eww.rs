@@ -0,0 +1,63 @@
+use super::{Element, OutputWriter};
+use crate::{format_list, settings::Settings};
+
+pub struct Eww;
+
+impl OutputWriter for Eww {
+    fn format(&self, element: &Element, _settings: &Settings) -> String {
+        let metadata = element.metadata;
+        let position = metadata.and_then(|v| v.position);
+        let art_path = metadata.map(|v| get_art_path(&v.art_url)).unwrap_or_default();
+
+        format!(
+            "{{\"players\": {}, \"selected\": {}, \"position\": {}, \"art_path\": {}}}",
+            format_list(element.players),
+            serde_json::Value::from(element.instance),
+            serde_json::Value::from(position),
+            serde_json::Value::from(art_path),
+        )
+    }
+
+    fn format_all(&self, _segments: &[String], selected: &Element, settings: &Settings) -> String {
+        // every player is in the JSON already
+        self.format(selected, settings)
+    }
+
+    fn formats_empty(&self) -> bool {
+        // the list of players can change without one being displayed
+        true
+    }
+
+    fn format_empty(&self) -> String {
+        String::from("{\"players\": [], \"selected\": \"\", \"position\": null, \"art_path\": \"\"}")
+    }
+}
+
+/// Path of a local cover art (`file://` URL), or an empty string
+pub fn get_art_path(art_url: &str) -> String {
+    let Some(path) = art_url.strip_prefix("file://") else {
+        return String::new();
+    };
+
+    // percent-decoding
+    let mut bytes: Vec<u8> = vec![];
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let decoded = match tail {
+            [a, b, ..] if byte == b'%' => std::str::from_utf8(&[*a, *b]).ok().and_then(|v| u8::from_str_radix(v, 16).ok()),
+            _ => None,
+        };
+        match decoded {
+            Some(v) => {
+                bytes.push(v);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
@@ -0,0 +1,157 @@
+use super::{Button, Element, OutputWriter};
+use crate::{settings::Settings, StreamMessage};
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+use tokio::sync::mpsc;
+
+/// Name of every block, told apart by their instance
+const BLOCK_NAME: &str = "mpris_widget";
+/// Instance of the block of a player in the line of every player, before the player's instance
+const PLAYER_BLOCK_PREFIX: &str = "player:";
+
+pub struct I3bar;
+
+/// Status block of the i3bar protocol
+#[derive(Serialize)]
+struct Block<'a> {
+    name: &'a str,
+    /// Action sent when the block is clicked
+    instance: &'a str,
+    full_text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<&'a str>,
+    separator: bool,
+}
+
+/// Click event sent by i3bar or swaybar on stdin
+#[derive(Deserialize)]
+pub struct ClickEvent {
+    pub name: Option<String>,
+    pub instance: Option<String>,
+    pub button: u8,
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+}
+
+impl OutputWriter for I3bar {
+    fn header(&self) -> Option<String> {
+        // the status lines are the elements of an infinite array
+        Some(String::from("{\"version\": 1, \"click_events\": true}\n[\n[]"))
+    }
+
+    fn format(&self, element: &Element, settings: &Settings) -> String {
+        let color = settings.colors.get(element.player, element.state);
+        // no block to click when the player does not allow its action
+        let enabled = |button: Button| element.metadata.is_none_or(|v| v.capabilities.resolve(button.as_str(), element.state).is_ok());
+        let button_block = |button: Button| Block {
+            name: BLOCK_NAME,
+            instance: button.as_str(),
+            full_text: settings.icons.get_button_icon(button, element.state),
+            color,
+            separator: false,
+        };
+
+        let mut blocks = vec![];
+        if enabled(Button::Previous) {
+            blocks.push(button_block(Button::Previous));
+        }
+        blocks.push(Block { name: BLOCK_NAME, instance: "play-pause", full_text: element.text, color, separator: false });
+        if enabled(Button::Next) {
+            blocks.push(button_block(Button::Next));
+        }
+        if let Some(last) = blocks.last_mut() {
+            last.separator = true;
+        }
+
+        String::from(",") + &serde_json::to_string(&blocks).unwrap_or_else(|_| String::from("[]"))
+    }
+
+    fn format_empty(&self) -> String {
+        String::from(",[]")
+    }
+
+    fn format_segment(&self, text: String, element: &Element, settings: &Settings) -> String {
+        let instance = String::from(PLAYER_BLOCK_PREFIX) + element.instance;
+        let color = settings.colors.get(element.player, element.state);
+        let block = Block { name: BLOCK_NAME, instance: &instance, full_text: &text, color, separator: true };
+
+        serde_json::to_string(&block).unwrap_or_default()
+    }
+
+    fn format_all(&self, segments: &[String], _selected: &Element, _settings: &Settings) -> String {
+        // one block per player, separated by the status bar instead of the joiner
+        String::from(",[") + &segments.join(",") + "]"
+    }
+}
+
+/// Action of a click on one of the blocks, if any
+pub fn get_click_action(event: &ClickEvent) -> Option<&'static str> {
+    if event.name.as_deref() != Some(BLOCK_NAME) {
+        return None;
+    }
+    let shift = event.modifiers.iter().any(|v| v == "Shift");
+
+    // the block of a player acts as play-pause, on that player
+    let instance = match event.instance.as_deref()? {
+        v if v.starts_with(PLAYER_BLOCK_PREFIX) => "play-pause",
+        v => v,
+    };
+
+    let action_name = match (instance, event.button) {
+        // wheel, anywhere
+        (_, 4) => "scroll-up",
+        (_, 5) => "scroll-down",
+        ("previous", 1) => "previous",
+        ("next", 1) => "next",
+        ("play-pause", 1) if shift => "stop",
+        ("play-pause", 1) => "play-pause",
+        ("play-pause", 2) => "previous",
+        ("play-pause", 3) => "next",
+        _ => return None,
+    };
+
+    Some(action_name)
+}
+
+/// Player targeted by a click on its block in the line of every player, if any
+pub fn get_click_player(event: &ClickEvent) -> Option<&str> {
+    event.instance.as_deref()?.strip_prefix(PLAYER_BLOCK_PREFIX)
+}
+
+/// Reads the click events from stdin and sends their actions to the daemon.
+/// Runs on its own thread, so a blocked read does not keep the daemon from quitting.
+pub(crate) fn read_click_events(tx: mpsc::Sender<StreamMessage>) {
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let line = match line {
+                Ok(v) => v,
+                Err(err) => {
+                    eprintln!("Could not read the click events: {err}");
+                    break;
+                }
+            };
+
+            // an infinite array: "[" first, then one event per line, after a comma
+            let line = line.trim().trim_start_matches(',');
+            if line.is_empty() || line == "[" {
+                continue;
+            }
+
+            let event: ClickEvent = match serde_json::from_str(line) {
+                Ok(v) => v,
+                Err(err) => {
+                    eprintln!("Invalid click event: {err}");
+                    continue;
+                }
+            };
+
+            if let Some(action_name) = get_click_action(&event) {
+                let player = get_click_player(&event).unwrap_or_default();
+                let message = StreamMessage::build(format!("{} {}", action_name, player)).expect("infallible");
+                if tx.blocking_send(message).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,272 @@
+use crate::{
+    settings::{IconSettings, Settings},
+    template, PlayerMetadata,
+};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::error::Error;
+
+mod eww;
+mod i3bar;
+mod polybar;
+mod tmux;
+mod waybar;
+mod xmobar;
+mod yambar;
+
+pub use eww::get_art_path;
+pub use i3bar::{get_click_action, get_click_player, ClickEvent};
+pub(crate) use i3bar::read_click_events;
+pub use polybar::{escape_polybar, escape_polybar_action};
+// lemonbar has the same formatting tags
+pub use polybar::{escape_polybar as escape_lemonbar, escape_polybar_action as escape_lemonbar_action};
+pub use tmux::escape_tmux;
+pub use waybar::escape_pango;
+pub use xmobar::escape_xmobar;
+pub use yambar::escape_yambar;
+
+/// Mouse buttons of the click tags and the actions they send
+const CLICK_ACTIONS: [(u8, &str); 5] = [
+    (1, "play-pause"),
+    (2, "previous"),
+    (3, "next"),
+    (4, "scroll-up"),
+    (5, "scroll-down"),
+];
+
+/// Buttons of the separate output modules (`mpris_widget watch --button`)
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Button {
+    Previous,
+    PlayPause,
+    Next,
+}
+
+impl Button {
+    pub const ALL: [Button; 3] = [Button::Previous, Button::PlayPause, Button::Next];
+
+    /// Also the action sent by a click on the button
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Button::Previous => "previous",
+            Button::PlayPause => "play-pause",
+            Button::Next => "next",
+        }
+    }
+}
+
+/// What the daemon displays
+pub struct Element<'a> {
+    pub text: &'a str,
+    pub player: &'a str,
+    /// e.g.: "Playing"
+    pub state: &'a str,
+    pub instance: &'a str,
+    pub metadata: Option<&'a PlayerMetadata>,
+    /// Every player, including the one displayed
+    pub players: &'a [PlayerMetadata],
+}
+
+/// Writes the line of an element for a given status bar
+pub trait OutputWriter {
+    /// Printed once, before the first line
+    fn header(&self) -> Option<String> {
+        None
+    }
+
+    /// Only called with an empty text if `formats_empty`
+    fn format(&self, element: &Element, settings: &Settings) -> String;
+
+    /// True if the line has something to show even without a player displayed
+    fn formats_empty(&self) -> bool {
+        false
+    }
+
+    /// Printed when there is nothing to display
+    fn format_empty(&self) -> String {
+        String::new()
+    }
+
+    /// Escapes the values of the segment templates (see `format_all`)
+    fn get_escape(&self) -> fn(&str) -> String {
+        |v| String::from(v)
+    }
+
+    /// Wraps the text with a color tag, in the status bars that have some (see `add_tags`)
+    fn add_color(&self, text: String, _color: &str) -> String {
+        text
+    }
+
+    /// Wraps the text with a tag running `command` when clicked with `button`, in the status bars that have some
+    fn add_action(&self, text: String, _button: u8, _command: &str) -> String {
+        text
+    }
+
+    /// Segment of a player in the line of every player, from its rendered template:
+    /// with the click actions and the color of the player in the status bars that have some
+    fn format_segment(&self, text: String, element: &Element, settings: &Settings) -> String {
+        add_tags(self, text, element, settings, element.instance)
+    }
+
+    /// Line of every player, made of their segments, for the selected element
+    fn format_all(&self, segments: &[String], _selected: &Element, settings: &Settings) -> String {
+        segments.join(&settings.all_players.joiner)
+    }
+
+    /// Line of a button module: its icon, or nothing if the player does not allow its action
+    fn format_button(&self, button: Button, element: &Element, enabled: bool, settings: &Settings) -> String {
+        if enabled {
+            String::from(settings.icons.get_button_icon(button, element.state))
+        } else {
+            String::new()
+        }
+    }
+}
+
+/// Adds the color of the player and the click actions, sent to `player` if not empty
+fn add_tags<W: OutputWriter + ?Sized>(writer: &W, mut text: String, element: &Element, settings: &Settings, player: &str) -> String {
+    if let Some(color) = settings.colors.get(element.player, element.state) {
+        text = writer.add_color(text, color);
+    }
+
+    for (button, action_name) in CLICK_ACTIONS.iter() {
+        text = writer.add_action(text, *button, &get_action_command(action_name, player));
+    }
+
+    text
+}
+
+/// Status bars the daemon can print for
+#[derive(Clone, Copy, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// JSON of a Waybar custom module
+    #[default]
+    Waybar,
+    /// Text with Polybar action and color tags
+    Polybar,
+    /// i3bar (and swaybar) protocol, with click events read from stdin
+    I3bar,
+    /// JSON of every player, for an Eww `deflisten`
+    Eww,
+    /// Text with tmux style tags, e.g. for `mpris_widget print` in `status-right`
+    Tmux,
+    /// Text with lemonbar action and color tags, the same as Polybar's
+    Lemonbar,
+    /// Text with xmobar action and color markup
+    Xmobar,
+    /// Tags of a yambar script module
+    Yambar,
+}
+
+impl OutputFormat {
+    fn get_writer(&self) -> &'static dyn OutputWriter {
+        match self {
+            OutputFormat::Waybar => &waybar::Waybar,
+            OutputFormat::Polybar => &polybar::Polybar,
+            OutputFormat::I3bar => &i3bar::I3bar,
+            OutputFormat::Eww => &eww::Eww,
+            OutputFormat::Tmux => &tmux::Tmux,
+            OutputFormat::Lemonbar => &polybar::Polybar,
+            OutputFormat::Xmobar => &xmobar::Xmobar,
+            OutputFormat::Yambar => &yambar::Yambar,
+        }
+    }
+
+    pub fn header(&self) -> Option<String> {
+        self.get_writer().header()
+    }
+
+    /// True if the status bar sends click events on stdin
+    pub fn has_click_events(&self) -> bool {
+        matches!(self, OutputFormat::I3bar)
+    }
+
+    pub fn format_empty(&self) -> String {
+        self.get_writer().format_empty()
+    }
+
+    /// Formats a button of the element, or returns an empty line without a player
+    pub fn format_button(&self, button: Button, element: &Element, settings: &Settings) -> String {
+        let Some(data) = element.metadata else {
+            return String::new();
+        };
+        let enabled = data.capabilities.resolve(button.as_str(), element.state).is_ok();
+
+        self.get_writer().format_button(button, element, enabled, settings)
+    }
+
+    /// Formats every element, `selected` highlighted, or returns an empty line without any
+    pub fn format_all(&self, elements: &[Element], selected: usize, settings: &Settings) -> String {
+        let Some(selected_element) = elements.get(selected) else {
+            return self.format_empty();
+        };
+        let writer = self.get_writer();
+        let all_players = &settings.all_players;
+
+        let segments: Vec<String> = elements
+            .iter()
+            .enumerate()
+            .map(|(index, element)| {
+                let template = if index == selected { &all_players.selected } else { &all_players.segment };
+                // checked when the settings are loaded
+                let text = render(template, element, &settings.icons, writer.get_escape()).unwrap_or_default();
+                writer.format_segment(text, element, settings)
+            })
+            .collect();
+
+        writer.format_all(&segments, selected_element, settings)
+    }
+
+    /// Formats the element, or returns an empty line if there is nothing to display
+    pub fn format(&self, element: &Element, settings: &Settings) -> String {
+        if element.text.is_empty() && !self.get_writer().formats_empty() {
+            self.format_empty()
+        } else {
+            self.get_writer().format(element, settings)
+        }
+    }
+}
+
+/// Renders a template of the settings (e.g.: the Waybar tooltip) with the values of the element.
+/// The values are escaped with `escape`, the rest of the template is kept as is (e.g.: markup).
+/// Placeholders: text, player, instance, state, state_icon, player_icon, artist, title, album, art_url,
+/// station, position, length
+pub fn render(template: &str, element: &Element, icons: &IconSettings, escape: fn(&str) -> String) -> Result<String, Box<dyn Error>> {
+    let metadata = element.metadata;
+    let field = |get: fn(&PlayerMetadata) -> &str| metadata.map(|v| String::from(get(v))).unwrap_or_default();
+
+    template::render(template, |name| {
+        let value = match name {
+            "text" => String::from(element.text),
+            "player" => String::from(element.player),
+            "instance" => String::from(element.instance),
+            "state" => element.state.to_lowercase(),
+            "state_icon" => String::from(icons.get_state_icon(element.state)),
+            "player_icon" => String::from(icons.get_player_icon(element.player)),
+            "artist" => field(|v| &v.artist),
+            "title" => field(|v| &v.title),
+            "album" => field(|v| &v.album),
+            "art_url" => field(|v| &v.art_url),
+            "station" => field(|v| &v.station),
+            "position" => metadata.and_then(|v| v.position).map(template::format_duration).unwrap_or_default(),
+            "length" => metadata.and_then(|v| v.length).map(template::format_duration).unwrap_or_default(),
+            _ => return None,
+        };
+        Some(escape(&value))
+    })
+}
+
+/// Command line calling back into this binary, for the click actions of the status bars.
+/// Without a player, the action controls the one displayed by the daemon.
+pub(crate) fn get_action_command(action_name: &str, player: &str) -> String {
+    let exe = std::env::current_exe()
+        .map(|v| v.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| String::from("mpris_widget"));
+
+    if player.is_empty() {
+        format!("{} action {}", exe, action_name)
+    } else {
+        format!("{} action {} {}", exe, action_name, player)
+    }
+}
@@ -0,0 +1,34 @@
+use super::{add_tags, Element, OutputWriter};
+use crate::settings::Settings;
+
+/// Also the writer of lemonbar, which has the same formatting tags
+pub struct Polybar;
+
+impl OutputWriter for Polybar {
+    fn format(&self, element: &Element, settings: &Settings) -> String {
+        add_tags(self, escape_polybar(element.text), element, settings, "")
+    }
+
+    fn get_escape(&self) -> fn(&str) -> String {
+        escape_polybar
+    }
+
+    fn add_color(&self, text: String, color: &str) -> String {
+        format!("%{{F{}}}{}%{{F-}}", color, text)
+    }
+
+    fn add_action(&self, text: String, button: u8, command: &str) -> String {
+        format!("%{{A{}:{}:}}{}%{{A}}", button, escape_polybar_action(command), text)
+    }
+}
+
+/// Escapes the text so the status bar does not read it as formatting tags ("%%" is drawn as "%")
+pub fn escape_polybar(v: &str) -> String {
+    v.replace('%', "%%")
+}
+
+/// Escapes the command of an action tag, which ends at the first unescaped ':'.
+/// '%' is kept: the command is run as written, "%%" would not be read back as "%".
+pub fn escape_polybar_action(v: &str) -> String {
+    v.replace(':', "\\:")
+}
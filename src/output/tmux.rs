@@ -0,0 +1,24 @@
+use super::{add_tags, Element, OutputWriter};
+use crate::settings::Settings;
+
+pub struct Tmux;
+
+impl OutputWriter for Tmux {
+    fn format(&self, element: &Element, settings: &Settings) -> String {
+        // tmux has no click actions, only the color is added
+        add_tags(self, escape_tmux(element.text), element, settings, "")
+    }
+
+    fn get_escape(&self) -> fn(&str) -> String {
+        escape_tmux
+    }
+
+    fn add_color(&self, text: String, color: &str) -> String {
+        format!("#[fg={}]{}#[default]", color, text)
+    }
+}
+
+/// Escapes the text so tmux does not read it as formats or style tags
+pub fn escape_tmux(v: &str) -> String {
+    v.replace('#', "##")
+}
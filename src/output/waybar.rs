@@ -0,0 +1,60 @@
+use super::{Button, Element, OutputWriter};
+use crate::settings::Settings;
+use serde_json::Value;
+
+pub struct Waybar;
+
+impl OutputWriter for Waybar {
+    fn format(&self, element: &Element, settings: &Settings) -> String {
+        format_module(&escape_pango(element.text), element, settings)
+    }
+
+    fn get_escape(&self) -> fn(&str) -> String {
+        escape_pango
+    }
+
+    fn format_all(&self, segments: &[String], selected: &Element, settings: &Settings) -> String {
+        // the segments are escaped already, the joiner is markup
+        format_module(&segments.join(&settings.all_players.joiner), selected, settings)
+    }
+
+    fn format_button(&self, button: Button, element: &Element, enabled: bool, settings: &Settings) -> String {
+        let icon = escape_pango(settings.icons.get_button_icon(button, element.state));
+        let disabled = if enabled { "" } else { ", \"disabled\"" };
+
+        // still shown when disabled, to be styled with the `disabled` class
+        format!(
+            "{{\"text\": {}, \"class\": [{}, \"{}\"{}], \"alt\": \"{}\"}}",
+            Value::from(icon), Value::from(format!("custom-{}", element.player)), button.as_str(), disabled, button.as_str()
+        )
+    }
+}
+
+/// JSON of the module, with `text` as Pango markup
+fn format_module(text: &str, element: &Element, settings: &Settings) -> String {
+    let Element { player, state, instance, .. } = element;
+
+    // checked when the settings are loaded
+    let tooltip = super::render(&settings.waybar.tooltip, element, &settings.icons, escape_pango).unwrap_or_default();
+
+    format!(
+        "{{\"text\": {}, \"class\": [{}, \"{}\"], \"alt\": {}, \"tooltip\": {}, \"state\": \"{}\", \"instance\": {}}}",
+        Value::from(text), Value::from(format!("custom-{}", player)), state.to_lowercase(), Value::from(*player), Value::from(tooltip), state.to_lowercase(), Value::from(*instance)
+    )
+}
+
+/// Escapes the text so Pango does not read it as markup
+pub fn escape_pango(v: &str) -> String {
+    let mut result = String::with_capacity(v.len());
+    for c in v.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '\'' => result.push_str("&apos;"),
+            '"' => result.push_str("&quot;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
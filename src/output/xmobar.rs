@@ -0,0 +1,27 @@
+use super::{add_tags, Element, OutputWriter};
+use crate::settings::Settings;
+
+pub struct Xmobar;
+
+impl OutputWriter for Xmobar {
+    fn format(&self, element: &Element, settings: &Settings) -> String {
+        add_tags(self, escape_xmobar(element.text), element, settings, "")
+    }
+
+    fn get_escape(&self) -> fn(&str) -> String {
+        escape_xmobar
+    }
+
+    fn add_color(&self, text: String, color: &str) -> String {
+        format!("<fc={}>{}</fc>", color, text)
+    }
+
+    fn add_action(&self, text: String, button: u8, command: &str) -> String {
+        format!("<action=`{}` button={}>{}</action>", command.replace('`', ""), button, text)
+    }
+}
+
+/// Escapes the text so xmobar does not read it as markup
+pub fn escape_xmobar(v: &str) -> String {
+    v.replace('<', "<raw=1:</>")
+}
@@ -0,0 +1,47 @@
+use super::{Element, OutputWriter};
+use crate::settings::Settings;
+
+pub struct Yambar;
+
+impl OutputWriter for Yambar {
+    fn format(&self, element: &Element, _settings: &Settings) -> String {
+        let metadata = element.metadata;
+        let value = |get: fn(&crate::PlayerMetadata) -> &str| metadata.map(|v| escape_yambar(get(v))).unwrap_or_default();
+
+        let tags = [
+            format!("text|string|{}", escape_yambar(element.text)),
+            format!("player|string|{}", escape_yambar(element.player)),
+            format!("instance|string|{}", escape_yambar(element.instance)),
+            format!("state|string|{}", element.state.to_lowercase()),
+            format!("playing|bool|{}", element.state == "Playing"),
+            format!("artist|string|{}", value(|v| &v.artist)),
+            format!("title|string|{}", value(|v| &v.title)),
+            format!("album|string|{}", value(|v| &v.album)),
+            format!("art_url|string|{}", value(|v| &v.art_url)),
+            format!("station|string|{}", value(|v| &v.station)),
+        ];
+
+        // a transaction ends with an empty line
+        tags.join("\n") + "\n"
+    }
+
+    fn format_all(&self, segments: &[String], selected: &Element, settings: &Settings) -> String {
+        // the segments are the text tag of the selected player
+        let text = segments.join(&settings.all_players.joiner);
+        self.format(&Element { text: &text, ..*selected }, settings)
+    }
+
+    fn formats_empty(&self) -> bool {
+        // every tag, even without a player, so yambar clears the previous ones
+        true
+    }
+
+    fn format_empty(&self) -> String {
+        self.format(&Element { text: "", player: "", state: "", instance: "", metadata: None, players: &[] }, &Settings::default())
+    }
+}
+
+/// Keeps the value on its line, a tag per line
+pub fn escape_yambar(v: &str) -> String {
+    v.replace(['\n', '\r'], " ")
+}
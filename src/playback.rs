@@ -0,0 +1,87 @@
+use crate::{
+    events::{Event, EventKind},
+    settings::PlaybackSettings,
+    PlayerMetadata, State,
+};
+
+/// Player that started playing and pauses the others, with exclusive playback.
+/// If several started at once, the last one listed.
+pub fn get_started<'a>(settings: &PlaybackSettings, changes: &'a [Event]) -> Option<&'a PlayerMetadata> {
+    changes
+        .iter()
+        .rev()
+        .find(|v| {
+            matches!(v.event, EventKind::PlayerAdded | EventKind::StateChanged)
+                && v.player.state == State::Playing
+                && !settings.coexist.contains(&v.player.player)
+        })
+        .map(|v| &v.player)
+}
+
+/// Players to pause because another one started playing (see `get_started`)
+pub fn get_interrupted<'a>(settings: &PlaybackSettings, changes: &[Event], players: &'a [PlayerMetadata]) -> Vec<&'a PlayerMetadata> {
+    let Some(started) = get_started(settings, changes) else {
+        return vec![];
+    };
+
+    players
+        .iter()
+        .filter(|v| v.instance != started.instance && v.state == State::Playing && !settings.coexist.contains(&v.player))
+        .collect()
+}
+
+/// Player paused by the daemon because another one started playing
+pub struct Interruption {
+    /// Instance of the paused player
+    pub paused: String,
+    /// Instance of the player that started playing
+    pub by: String,
+    /// Player displayed when it was paused, restored when it is resumed
+    pub selected_player: String,
+    pub selected_instance: String,
+    /// How the displayed player was chosen (see `Status::selection`)
+    pub selection: &'static str,
+}
+
+/// Players paused by the daemon, the most recent last
+#[derive(Default)]
+pub struct PlaybackStack {
+    interruptions: Vec<Interruption>,
+}
+
+impl PlaybackStack {
+    pub fn push(&mut self, interruption: Interruption) {
+        self.interruptions.push(interruption);
+    }
+
+    /// Removes and returns the interruptions to resume, the most recent first: the player that interrupted
+    /// stopped playing or vanished. They are forgotten instead if another player plays by then, and so are
+    /// the interruptions of the players resumed by the user or gone.
+    pub fn take_ended(&mut self, settings: &PlaybackSettings, changes: &[Event], players: &[PlayerMetadata]) -> Vec<Interruption> {
+        let find = |instance: &str| players.iter().find(|v| v.instance == instance);
+        // from the changes, as a player may still be listed as playing right after it was paused
+        let resumed = |instance: &str| {
+            changes
+                .iter()
+                .any(|v| v.event == EventKind::StateChanged && v.player.instance == instance && v.player.state == State::Playing)
+        };
+
+        self.interruptions.retain(|v| find(&v.paused).is_some() && !resumed(&v.paused));
+
+        // a player that was paused itself waits for the end of its own interruption
+        let paused: Vec<String> = self.interruptions.iter().map(|v| v.paused.clone()).collect();
+        let (mut ended, interruptions): (Vec<Interruption>, Vec<Interruption>) = std::mem::take(&mut self.interruptions)
+            .into_iter()
+            .partition(|v| !find(&v.by).is_some_and(|data| data.state == State::Playing) && !paused.contains(&v.by));
+        self.interruptions = interruptions;
+        ended.reverse();
+
+        // resumed players would pause the ones playing now
+        let others_playing = players.iter().any(|v| v.state == State::Playing && !settings.coexist.contains(&v.player));
+        if others_playing {
+            return vec![];
+        }
+
+        ended
+    }
+}
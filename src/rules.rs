@@ -0,0 +1,77 @@
+use crate::PlayerMetadata;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::borrow::Cow;
+
+/// Field of the metadata a rule rewrites
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleField {
+    Title,
+    Artist,
+    Album,
+}
+
+impl RuleField {
+    pub const ALL: [RuleField; 3] = [RuleField::Title, RuleField::Artist, RuleField::Album];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleField::Title => "title",
+            RuleField::Artist => "artist",
+            RuleField::Album => "album",
+        }
+    }
+
+    pub fn get<'a>(&self, data: &'a PlayerMetadata) -> &'a str {
+        match self {
+            RuleField::Title => &data.title,
+            RuleField::Artist => &data.artist,
+            RuleField::Album => &data.album,
+        }
+    }
+
+    pub fn get_mut<'a>(&self, data: &'a mut PlayerMetadata) -> &'a mut String {
+        match self {
+            RuleField::Title => &mut data.title,
+            RuleField::Artist => &mut data.artist,
+            RuleField::Album => &mut data.album,
+        }
+    }
+}
+
+/// Find and replace in a field of the metadata, e.g.:
+/// `field = "title"`, `find = " \(Official Video\)"`, `players = ["firefox", "chromium"]`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Names of the players the rule applies to, all if empty
+    #[serde(default)]
+    pub players: Vec<String>,
+    pub field: RuleField,
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub find: Regex,
+    /// Replacement, with `$1` or `${name}` for the groups of `find`
+    #[serde(default)]
+    pub replace: String,
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+/// Applies the rules in order to the metadata of a player
+pub fn apply(rules: &[Rule], data: &mut PlayerMetadata) {
+    for rule in rules.iter() {
+        if !rule.players.is_empty() && !rule.players.iter().any(|v| v == &data.player) {
+            continue;
+        }
+
+        let value = rule.field.get_mut(data);
+        // borrowed if nothing matched, the value is then kept as is
+        if let Cow::Owned(result) = rule.find.replace_all(value, rule.replace.as_str()) {
+            *value = String::from(result.trim());
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::settings::MouseSettings;
+use serde::Deserialize;
+use tokio::time::Instant;
+
+/// What scrolling over the widget does
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrollMode {
+    /// Moves the position forward (up) or backward (down)
+    Seek,
+    /// Raises (up) or lowers (down) the volume
+    Volume,
+    /// Goes to the next (up) or previous (down) track
+    Track,
+    /// Does nothing
+    None,
+}
+
+/// Scroll events received by the daemon, not executed yet
+pub(crate) struct PendingScroll {
+    /// Player scrolled over, the one displayed if empty
+    pub player: String,
+    /// Sum of the events: +1 for each scroll up, -1 for each scroll down
+    pub steps: i32,
+    /// When the events are executed as one call
+    pub until: Instant,
+}
+
+/// Returns +1 for "scroll-up", -1 for "scroll-down", None for other actions
+pub fn get_step(action_name: &str) -> Option<i32> {
+    match action_name {
+        "scroll-up" => Some(1),
+        "scroll-down" => Some(-1),
+        _ => None,
+    }
+}
+
+/// Arguments of playerctl doing `steps` scroll events at once, or None if there is nothing to do
+pub fn get_playerctl_args(mode: ScrollMode, steps: i32, settings: &MouseSettings) -> Option<Vec<String>> {
+    if steps == 0 {
+        return None;
+    }
+    let sign = if steps > 0 { "+" } else { "-" };
+    let count = steps.unsigned_abs();
+
+    let args = match mode {
+        ScrollMode::Seek => vec![String::from("position"), format!("{}{}", settings.seek_step * count as u64, sign)],
+        ScrollMode::Volume => vec![String::from("volume"), format!("{:.2}{}", settings.volume_step * count as f64, sign)],
+        // one track at a time, however fast the wheel goes
+        ScrollMode::Track if steps > 0 => vec![String::from("next")],
+        ScrollMode::Track => vec![String::from("previous")],
+        ScrollMode::None => return None,
+    };
+
+    Some(args)
+}
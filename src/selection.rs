@@ -0,0 +1,46 @@
+use crate::PlayerMetadata;
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, io::ErrorKind};
+
+const DEFAULT_SELECTION_FILE: &str = "mpris-widget/selection.json";
+
+/// Player selected by the user, kept across restarts of the daemon
+#[derive(Serialize, Deserialize)]
+pub struct SavedSelection {
+    pub player: String,
+    pub instance: String,
+}
+
+impl SavedSelection {
+    /// Reads the saved selection, if any
+    pub fn load() -> Result<Option<SavedSelection>, Box<dyn Error>> {
+        let file_path = get_selection_file_path();
+
+        let content = match fs::read_to_string(&file_path) {
+            Ok(v) => v,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(format!("{} => {}", file_path, err).into()),
+        };
+
+        match serde_json::from_str(&content) {
+            Ok(v) => Ok(Some(v)),
+            Err(err) => Err(format!("{} => {}", file_path, err).into()),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        crate::write_to_file_atomically(&get_selection_file_path(), &serde_json::to_string(self)?)
+    }
+}
+
+pub fn get_selection_file_path() -> String {
+    crate::get_xdg_dir("XDG_STATE_HOME", "$HOME/.local/state") + "/" + DEFAULT_SELECTION_FILE
+}
+
+/// Index of the player to display: the selected instance, else another instance of the selected player,
+/// else the first one. 0 if there are no players.
+pub fn pick_player(players: &[PlayerMetadata], selected_instance: &str, selected_player: &str) -> usize {
+    players.iter().position(|data| data.instance.eq(selected_instance))
+        .or_else(|| players.iter().position(|data| data.player.eq(selected_player)))
+        .unwrap_or(0)
+}
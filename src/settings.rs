@@ -0,0 +1,443 @@
+use crate::{
+    events::EventKind,
+    icons::IconPreset,
+    rules::Rule,
+    output::{self, Button, Element, OutputFormat},
+    scroll::ScrollMode,
+    status::Status,
+};
+use serde::Deserialize;
+use std::{collections::HashMap, env, error::Error, fs, io::ErrorKind};
+
+const DEFAULT_CONFIG_FILE: &str = "mpris-widget/config.toml";
+
+/// Settings of the daemon, read from the configuration file.
+/// Missing keys take their default value.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Milliseconds between two fetches of the players' metadata
+    pub interval: u64,
+    pub hooks: HooksSettings,
+    pub mouse: MouseSettings,
+    pub colors: ColorSettings,
+    pub waybar: WaybarSettings,
+    pub all_players: AllPlayersSettings,
+    pub icons: IconSettings,
+    pub streams: StreamSettings,
+    pub playback: PlaybackSettings,
+    /// Rewrites of the metadata, applied in order
+    pub rules: Vec<Rule>,
+    /// Where the current player is written, besides the standard output
+    pub sinks: Vec<SinkSettings>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            interval: 1000,
+            hooks: HooksSettings::default(),
+            mouse: MouseSettings::default(),
+            colors: ColorSettings::default(),
+            waybar: WaybarSettings::default(),
+            all_players: AllPlayersSettings::default(),
+            icons: IconSettings::default(),
+            streams: StreamSettings::default(),
+            playback: PlaybackSettings::default(),
+            rules: vec![],
+            sinks: vec![],
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the configuration file, or returns the defaults if there is none
+    pub fn load() -> Result<Settings, Box<dyn Error>> {
+        let file_path = get_config_file_path();
+
+        let content = match fs::read_to_string(&file_path) {
+            Ok(v) => v,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(err) => return Err(format!("{} => {}", file_path, err).into()),
+        };
+
+        let settings: Settings = match toml::from_str(&content) {
+            Ok(v) => v,
+            Err(err) => return Err(format!("{} => {}", file_path, err).into()),
+        };
+
+        if settings.interval == 0 {
+            return Err(format!("{} => 'interval' must be greater than 0", file_path).into());
+        }
+        if settings.mouse.volume_step <= 0.0 || settings.mouse.volume_step > 1.0 {
+            return Err(format!("{} => 'mouse.volume_step' must be between 0 and 1", file_path).into());
+        }
+
+        settings.colors.validate().map_err(|err| format!("{} => {}", file_path, err))?;
+        if settings.streams.separator.is_empty() {
+            return Err(format!("{} => 'streams.separator' must not be empty", file_path).into());
+        }
+
+        settings.waybar.validate().map_err(|err| format!("{} => waybar: {}", file_path, err))?;
+        settings.all_players.validate().map_err(|err| format!("{} => all_players: {}", file_path, err))?;
+        for sink in settings.sinks.iter() {
+            sink.validate().map_err(|err| format!("{} => sinks: {}", file_path, err))?;
+        }
+
+        Ok(settings)
+    }
+}
+
+/// Shell commands executed when a player changes
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksSettings {
+    /// Milliseconds before a hook is killed
+    pub timeout: u64,
+    pub player_added: Option<String>,
+    pub player_removed: Option<String>,
+    pub track_changed: Option<String>,
+    pub state_changed: Option<String>,
+    pub selection_changed: Option<String>,
+    pub seeked: Option<String>,
+    pub volume_changed: Option<String>,
+}
+
+impl Default for HooksSettings {
+    fn default() -> Self {
+        Self {
+            timeout: 5000,
+            player_added: None,
+            player_removed: None,
+            track_changed: None,
+            state_changed: None,
+            selection_changed: None,
+            seeked: None,
+            volume_changed: None,
+        }
+    }
+}
+
+impl HooksSettings {
+    pub fn get_command(&self, event: EventKind) -> Option<&str> {
+        let command = match event {
+            EventKind::PlayerAdded => &self.player_added,
+            EventKind::PlayerRemoved => &self.player_removed,
+            EventKind::TrackChanged => &self.track_changed,
+            EventKind::StateChanged => &self.state_changed,
+            EventKind::SelectionChanged => &self.selection_changed,
+            EventKind::Seeked => &self.seeked,
+            EventKind::VolumeChanged => &self.volume_changed,
+        };
+        command.as_deref().filter(|v| !v.trim().is_empty())
+    }
+}
+
+/// What the mouse does over the widget
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MouseSettings {
+    /// Milliseconds during which scroll events are added up into one call
+    pub debounce: u64,
+    /// Default scroll mode
+    pub scroll: ScrollMode,
+    /// Scroll mode by player name (e.g.: mpv = "seek")
+    pub players: HashMap<String, ScrollMode>,
+    /// Seconds sought per scroll event
+    pub seek_step: u64,
+    /// Volume changed per scroll event, from 0.0 to 1.0
+    pub volume_step: f64,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            debounce: 200,
+            scroll: ScrollMode::Volume,
+            players: HashMap::new(),
+            seek_step: 5,
+            volume_step: 0.05,
+        }
+    }
+}
+
+impl MouseSettings {
+    pub fn get_scroll_mode(&self, player: &str) -> ScrollMode {
+        self.players.get(player).copied().unwrap_or(self.scroll)
+    }
+}
+
+/// Colors of the text (e.g.: "#1db954"), in the formats that support them
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorSettings {
+    pub playing: Option<String>,
+    pub paused: Option<String>,
+    pub stopped: Option<String>,
+    /// Color by player name, over the color of the state
+    pub players: HashMap<String, String>,
+}
+
+impl ColorSettings {
+    /// Color of a player in a state (e.g.: "Playing"), if any
+    pub fn get(&self, player: &str, state: &str) -> Option<&str> {
+        let color = match self.players.get(player) {
+            Some(v) => Some(v),
+            None => match state {
+                "Playing" => self.playing.as_ref(),
+                "Paused" => self.paused.as_ref(),
+                "Stopped" => self.stopped.as_ref(),
+                _ => None,
+            },
+        };
+        color.map(|v| v.as_str())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let colors = [&self.playing, &self.paused, &self.stopped].into_iter().flatten().chain(self.players.values());
+
+        for color in colors {
+            let hex = color.strip_prefix('#').unwrap_or_default();
+            if !matches!(hex.len(), 6 | 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("'{}' is not a color, expected #rrggbb or #aarrggbb", color));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Stream titles such as "Artist - Title" sent without an artist by internet radios (ICY metadata)
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StreamSettings {
+    /// Names of the players whose titles are split, none by default
+    pub split_title: Vec<String>,
+    /// Between the artist and the title
+    pub separator: String,
+}
+
+impl Default for StreamSettings {
+    fn default() -> Self {
+        Self {
+            split_title: vec![],
+            separator: String::from(" - "),
+        }
+    }
+}
+
+/// What the daemon does when players start or stop playing
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlaybackSettings {
+    /// Pause the other players when one starts playing
+    pub exclusive: bool,
+    /// Names of the players that play along the others, never paused and never pausing
+    pub coexist: Vec<String>,
+    /// With `exclusive`, play again the players paused by the daemon once the one that interrupted them
+    /// stops or vanishes, and display them again
+    pub resume: bool,
+}
+
+/// Icons of the states and of the players: `{state_icon}` and `{player_icon}` in the templates.
+/// The icons that are set replace the ones of the preset.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IconSettings {
+    pub preset: IconPreset,
+    pub playing: Option<String>,
+    pub paused: Option<String>,
+    pub stopped: Option<String>,
+    /// Of the `previous` button module
+    pub previous: Option<String>,
+    /// Of the `next` button module
+    pub next: Option<String>,
+    /// Icon by player name
+    pub players: HashMap<String, String>,
+    /// Icon of the players without one
+    pub default_player: Option<String>,
+}
+
+impl IconSettings {
+    /// Icon of a state (e.g.: "Playing"), empty if unknown
+    pub fn get_state_icon(&self, state: &str) -> &str {
+        let icon_set = self.preset.get_icon_set();
+
+        match state {
+            "Playing" => self.playing.as_deref().unwrap_or(icon_set.playing),
+            "Paused" => self.paused.as_deref().unwrap_or(icon_set.paused),
+            "Stopped" => self.stopped.as_deref().unwrap_or(icon_set.stopped),
+            _ => "",
+        }
+    }
+
+    /// Icon of a button module, the one of the state (e.g.: "Playing") for play-pause
+    pub fn get_button_icon(&self, button: Button, state: &str) -> &str {
+        let icon_set = self.preset.get_icon_set();
+
+        match button {
+            Button::Previous => self.previous.as_deref().unwrap_or(icon_set.previous),
+            Button::PlayPause => self.get_state_icon(state),
+            Button::Next => self.next.as_deref().unwrap_or(icon_set.next),
+        }
+    }
+
+    /// Icon of a player name, or the default one
+    pub fn get_player_icon(&self, player: &str) -> &str {
+        let icon_set = self.preset.get_icon_set();
+
+        if let Some(icon) = self.players.get(player) {
+            return icon;
+        }
+        if let Some((_, icon)) = icon_set.players.iter().find(|(name, _)| *name == player) {
+            return icon;
+        }
+        self.default_player.as_deref().unwrap_or(icon_set.default_player)
+    }
+}
+
+/// Output of the `waybar` format
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaybarSettings {
+    /// Pango markup of the tooltip, the values of the placeholders are escaped
+    pub tooltip: String,
+}
+
+impl Default for WaybarSettings {
+    fn default() -> Self {
+        Self {
+            tooltip: String::from(
+                "<b>{title}</b>{?artist:\n{artist}}{?album:\n<i>{album}</i>}{?length:\n{?position:{position} / }{length}}\n<small>{player} ({instance})</small>",
+            ),
+        }
+    }
+}
+
+impl WaybarSettings {
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        let element = Element { text: "", player: "", state: "", instance: "", metadata: None, players: &[] };
+        output::render(&self.tooltip, &element, &IconSettings::default(), output::escape_pango)?;
+        Ok(())
+    }
+}
+
+/// Line of every player (`mpris_widget daemon --all-players`)
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AllPlayersSettings {
+    /// Between two segments, kept as is (e.g.: markup)
+    pub joiner: String,
+    pub order: PlayerOrder,
+    /// Template of the segment of a player, with the placeholders of the Waybar tooltip
+    pub segment: String,
+    /// Template of the segment of the selected player
+    pub selected: String,
+}
+
+impl Default for AllPlayersSettings {
+    fn default() -> Self {
+        Self {
+            joiner: String::from(" | "),
+            order: PlayerOrder::default(),
+            segment: String::from("{text}"),
+            selected: String::from("[{text}]"),
+        }
+    }
+}
+
+impl AllPlayersSettings {
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        let element = Element { text: "", player: "", state: "", instance: "", metadata: None, players: &[] };
+        output::render(&self.segment, &element, &IconSettings::default(), |v| String::from(v))?;
+        output::render(&self.selected, &element, &IconSettings::default(), |v| String::from(v))?;
+        Ok(())
+    }
+}
+
+/// Order of the segments of the players
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlayerOrder {
+    /// As printed by the metadata command
+    #[default]
+    Listed,
+    /// Playing, then paused, then stopped
+    PlayingFirst,
+    /// The selected player, then the others as listed
+    SelectedFirst,
+    /// By player name, then instance
+    Name,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkKind {
+    /// Replaced atomically on each change
+    File,
+    /// Existing named pipe, skipped while nobody reads it
+    Fifo,
+    /// Lines sent to the clients of `mpris_widget watch --sink <name>`
+    Socket,
+}
+
+/// Changes a sink is written on
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkFilter {
+    /// Any change of the written content
+    #[default]
+    Content,
+    /// Another track, or another player
+    Track,
+    /// Playing, paused or stopped, or another player
+    State,
+    /// Another player
+    Player,
+}
+
+/// Output besides the standard output
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SinkSettings {
+    #[serde(rename = "type")]
+    pub kind: SinkKind,
+    /// File or named pipe, environment variables are expanded (e.g.: "$HOME/now_playing.txt")
+    pub path: Option<String>,
+    /// Subscription name of a socket sink
+    pub name: Option<String>,
+    /// Status bar format of the content
+    pub format: Option<OutputFormat>,
+    /// Template of the content, with the placeholders of `mpris_widget status --format`
+    pub template: Option<String>,
+    #[serde(default)]
+    pub filter: SinkFilter,
+    /// Minimum milliseconds between two writes, the last change is written afterwards
+    #[serde(default)]
+    pub min_interval: u64,
+}
+
+impl SinkSettings {
+    fn validate(&self) -> Result<(), String> {
+        match self.kind {
+            SinkKind::File | SinkKind::Fifo if self.path.as_deref().unwrap_or_default().is_empty() => {
+                return Err(String::from("'path' is required for files and named pipes"));
+            }
+            SinkKind::Socket if self.name.as_deref().unwrap_or_default().is_empty() => {
+                return Err(String::from("'name' is required for sockets"));
+            }
+            _ => {}
+        }
+
+        match (&self.format, &self.template) {
+            (Some(_), Some(_)) | (None, None) => Err(String::from("either 'format' or 'template' is required")),
+            (None, Some(template)) => Status::create(None, None, "", 0, &IconSettings::default()).format(template).map(|_| ()).map_err(|err| err.to_string()),
+            (Some(_), None) => Ok(()),
+        }
+    }
+}
+
+pub fn get_config_file_path() -> String {
+    env::var("MPRIS_WIDGET_CONFIG").unwrap_or_else(|_| {
+        crate::get_xdg_dir("XDG_CONFIG_HOME", "$HOME/.config") + "/" + DEFAULT_CONFIG_FILE
+    })
+}
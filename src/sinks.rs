@@ -0,0 +1,66 @@
+use crate::settings::{SinkKind, SinkSettings};
+use std::error::Error;
+use tokio::{net::unix::pipe, sync::watch, time::Instant};
+
+/// What was written to a sink, to filter and rate limit the next writes
+#[derive(Default)]
+pub(crate) struct SinkState {
+    /// Key of the last change (see `SinkFilter`)
+    key: Option<String>,
+    /// Content of a change waiting for the end of `min_interval`
+    pending: Option<String>,
+    written_at: Option<Instant>,
+}
+
+impl SinkState {
+    /// Records the content if its key changed (or if `force`),
+    /// then returns it if the sink can be written now
+    pub fn take(&mut self, settings: &SinkSettings, key: String, content: String, force: bool) -> Option<String> {
+        if force || self.key.as_ref() != Some(&key) {
+            self.key = Some(key);
+            self.pending = Some(content);
+        }
+
+        let min_interval = std::time::Duration::from_millis(settings.min_interval);
+        if self.written_at.is_some_and(|v| v.elapsed() < min_interval) {
+            return None;
+        }
+
+        let content = self.pending.take()?;
+        self.written_at = Some(Instant::now());
+        Some(content)
+    }
+}
+
+/// Writes the content to a file, a named pipe, or the subscribers of a socket sink
+pub(crate) async fn write(settings: &SinkSettings, content: &str, subscribers: Option<&watch::Sender<String>>) -> Result<(), Box<dyn Error>> {
+    let path = expand_path(settings.path.as_deref().unwrap_or_default());
+
+    match settings.kind {
+        SinkKind::File => crate::write_to_file_atomically(&path, &(String::from(content) + "\n"))?,
+        SinkKind::Fifo => {
+            // fails if nobody reads the pipe, the content is dropped
+            let sender = match pipe::OpenOptions::new().open_sender(&path) {
+                Ok(v) => v,
+                Err(err) if err.raw_os_error() == Some(6) => return Ok(()), // ENXIO
+                Err(err) => return Err(format!("{} => {}", path, err).into()),
+            };
+            // never waits for a slow reader, lines are shorter than the atomic size of a pipe
+            sender.try_write((String::from(content) + "\n").as_bytes())?;
+        }
+        SinkKind::Socket => {
+            // kept for the subscribers to come
+            if let Some(subscribers) = subscribers {
+                subscribers.send_replace(String::from(content));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn expand_path(path: &str) -> String {
+    let mut options = envmnt::ExpandOptions::new();
+    options.expansion_type = Some(envmnt::ExpansionType::Unix);
+    envmnt::expand(path, Some(options))
+}
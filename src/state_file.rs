@@ -0,0 +1,56 @@
+use crate::{read_first_line, status::StatusMetadata, PlayerMetadata};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, io::Read};
+
+/// Longest state file read back, metadata included (in bytes)
+const MAX_STATE_FILE_LEN: u64 = 64 * 1024;
+
+/// Current player, written into the output file with `--from-output-file`
+#[derive(Serialize, Deserialize)]
+pub struct StateFile {
+    pub player: String,
+    pub instance: String,
+    pub state: String,
+    pub metadata: StatusMetadata,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+impl StateFile {
+    pub(crate) fn create(metadata: Option<&PlayerMetadata>) -> Self {
+        match metadata {
+            Some(data) => Self {
+                player: data.player.clone(),
+                instance: data.instance.clone(),
+                state: String::from(data.get_state_str()),
+                metadata: StatusMetadata::create(data),
+                timestamp: crate::get_timestamp(),
+            },
+            None => Self {
+                player: String::new(),
+                instance: String::new(),
+                state: String::new(),
+                metadata: StatusMetadata::default(),
+                timestamp: crate::get_timestamp(),
+            },
+        }
+    }
+
+    /// True if both describe the same player in the same state, whenever they were written
+    pub(crate) fn is_same(&self, other: &StateFile) -> bool {
+        self.player == other.player && self.instance == other.instance && self.state == other.state && self.metadata == other.metadata
+    }
+}
+
+/// Reads the player to control from the output file: the instance of the JSON state file,
+/// or the first line of a legacy output file (`--legacy-output-file`)
+pub fn read_output_file_player(file_path: &str) -> Result<String, Box<dyn Error>> {
+    let mut content = String::new();
+    fs::File::open(file_path)?.take(MAX_STATE_FILE_LEN).read_to_string(&mut content)?;
+
+    match serde_json::from_str::<StateFile>(&content) {
+        Ok(state_file) if state_file.instance.is_empty() => Ok(state_file.player),
+        Ok(state_file) => Ok(state_file.instance),
+        Err(_) => read_first_line(file_path),
+    }
+}
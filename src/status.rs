@@ -0,0 +1,102 @@
+use crate::{settings::IconSettings, template, PlayerMetadata};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// State of the running daemon, answered to the `status` query.
+/// Positions and lengths are in microseconds, like in MPRIS.
+#[derive(Serialize, Deserialize)]
+pub struct Status {
+    pub player: String,
+    pub instance: String,
+    pub state: String,
+    #[serde(default)]
+    pub state_icon: String,
+    #[serde(default)]
+    pub player_icon: String,
+    pub metadata: StatusMetadata,
+    /// Estimated from the last fetch if the player is playing
+    pub position: Option<u64>,
+    /// How the player was chosen: "selected" by the user, "restored" from the
+    /// selection saved before the daemon restarted, or "first" listed
+    pub selection: String,
+    /// Seconds since the daemon started
+    pub uptime: u64,
+}
+
+#[derive(Default, PartialEq, Serialize, Deserialize)]
+pub struct StatusMetadata {
+    pub artist: String,
+    pub title: String,
+    pub album: String,
+    pub art_url: String,
+    /// Name of the internet radio, for the players whose stream titles are split
+    #[serde(default)]
+    pub station: String,
+    pub length: Option<u64>,
+}
+
+impl StatusMetadata {
+    pub(crate) fn create(data: &PlayerMetadata) -> Self {
+        Self {
+            artist: data.artist.clone(),
+            title: data.title.clone(),
+            album: data.album.clone(),
+            art_url: data.art_url.clone(),
+            station: data.station.clone(),
+            length: data.length,
+        }
+    }
+}
+
+impl Status {
+    pub(crate) fn create(metadata: Option<&PlayerMetadata>, position: Option<u64>, selection: &str, uptime: u64, icons: &IconSettings) -> Self {
+        match metadata {
+            Some(data) => Self {
+                player: data.player.clone(),
+                instance: data.instance.clone(),
+                state: String::from(data.get_state_str()),
+                state_icon: String::from(icons.get_state_icon(data.get_state_str())),
+                player_icon: String::from(icons.get_player_icon(&data.player)),
+                metadata: StatusMetadata::create(data),
+                position,
+                selection: String::from(selection),
+                uptime,
+            },
+            None => Self {
+                player: String::new(),
+                instance: String::new(),
+                state: String::new(),
+                state_icon: String::new(),
+                player_icon: String::new(),
+                metadata: StatusMetadata::default(),
+                position: None,
+                selection: String::from(selection),
+                uptime,
+            },
+        }
+    }
+
+    /// Renders a template such as `{artist} - {title} ({position}/{length})`
+    pub fn format(&self, template: &str) -> Result<String, Box<dyn Error>> {
+        template::render(template, |name| {
+            let value = match name {
+                "player" => self.player.clone(),
+                "instance" => self.instance.clone(),
+                "state" => self.state.clone(),
+                "state_icon" => self.state_icon.clone(),
+                "player_icon" => self.player_icon.clone(),
+                "artist" => self.metadata.artist.clone(),
+                "title" => self.metadata.title.clone(),
+                "album" => self.metadata.album.clone(),
+                "art_url" => self.metadata.art_url.clone(),
+                "station" => self.metadata.station.clone(),
+                "position" => self.position.map(template::format_duration).unwrap_or_default(),
+                "length" => self.metadata.length.map(template::format_duration).unwrap_or_default(),
+                "selection" => self.selection.clone(),
+                "uptime" => template::format_duration(self.uptime * 1_000_000),
+                _ => return None,
+            };
+            Some(value)
+        })
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{settings::StreamSettings, PlayerMetadata};
+
+/// Splits the title of a stream into the artist and the title when the player gives no artist,
+/// and sets the station, for the players of `split_title`
+pub fn apply(settings: &StreamSettings, data: &mut PlayerMetadata) {
+    if !settings.split_title.iter().any(|v| v == &data.player) {
+        return;
+    }
+
+    data.station = get_station(&data.album, &data.url);
+
+    if !data.artist.is_empty() {
+        return;
+    }
+    if let Some((artist, title)) = data.title.split_once(settings.separator.as_str()) {
+        let (artist, title) = (artist.trim(), title.trim());
+        if !artist.is_empty() && !title.is_empty() {
+            data.artist = String::from(artist);
+            data.title = String::from(title);
+            data.title_split = true;
+        }
+    }
+}
+
+/// The album if the player sets it to the name of the station (e.g.: VLC), or else the host of a network URL
+pub fn get_station(album: &str, url: &str) -> String {
+    if !album.is_empty() {
+        return String::from(album);
+    }
+
+    match url.split_once("://") {
+        Some((scheme, rest)) if scheme != "file" => {
+            let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+            // without the credentials and the port
+            let host = host.rsplit('@').next().unwrap_or_default();
+            String::from(host.split(':').next().unwrap_or_default())
+        }
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,80 @@
+//! Integration with systemd: socket activation and readiness notification.
+//! See sd_listen_fds(3) and sd_notify(3).
+
+use std::{
+    env,
+    error::Error,
+    mem::ManuallyDrop,
+    sync::atomic::{AtomicBool, Ordering},
+    os::{
+        fd::FromRawFd,
+        linux::net::SocketAddrExt,
+        unix::net::{SocketAddr, UnixDatagram, UnixListener},
+    },
+};
+
+/// First file descriptor passed by systemd
+const SD_LISTEN_FDS_START: i32 = 3;
+
+/// True once the socket passed by systemd was taken
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Takes the listening socket passed by systemd, if this process was socket-activated.
+/// The variables are kept, the environment is not safe to change once other threads run:
+/// child processes inherit them but ignore them, `LISTEN_PID` is not theirs.
+pub fn take_listener() -> Result<Option<UnixListener>, Box<dyn Error>> {
+    // the sockets are meant for another process
+    match env::var("LISTEN_PID").map(|v| v.parse::<u32>()) {
+        Ok(Ok(pid)) if pid == std::process::id() => {}
+        _ => return Ok(None),
+    }
+
+    // the variables stay set, a second listener would own the same descriptor
+    if TAKEN.swap(true, Ordering::SeqCst) {
+        return Ok(None);
+    }
+
+    let count: i32 = match env::var("LISTEN_FDS") {
+        Ok(v) => v.parse()?,
+        Err(_) => return Ok(None),
+    };
+
+    if count < 1 {
+        return Ok(None);
+    }
+    if count > 1 {
+        return Err(format!("expected 1 socket from systemd, got {}", count).into());
+    }
+
+    // SAFETY: systemd passes the socket at SD_LISTEN_FDS_START and nothing else owns it.
+    // Not closed until it is known to be a Unix socket, in case the descriptor is something else.
+    let listener = ManuallyDrop::new(unsafe { UnixListener::from_raw_fd(SD_LISTEN_FDS_START) });
+
+    // fails if the socket is not a Unix socket
+    listener.local_addr()?;
+
+    let listener = ManuallyDrop::into_inner(listener);
+    listener.set_nonblocking(true)?;
+
+    Ok(Some(listener))
+}
+
+/// Sends a state (e.g.: "READY=1") to the service manager.
+/// Does nothing if the daemon was not started by systemd.
+pub fn notify(state: &str) -> Result<(), Box<dyn Error>> {
+    let socket_path = match env::var("NOTIFY_SOCKET") {
+        Ok(v) if !v.is_empty() => v,
+        _ => return Ok(()),
+    };
+
+    let addr = match socket_path.strip_prefix('@') {
+        // abstract namespace
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes())?,
+        None => SocketAddr::from_pathname(&socket_path)?,
+    };
+
+    let socket = UnixDatagram::unbound()?;
+    socket.send_to_addr(state.as_bytes(), &addr)?;
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use std::{error::Error, iter::Peekable, str::Chars};
+
+/// Replaces the placeholders of `template` (e.g.: `{title}`) with the values returned by `lookup`.
+/// `{?album:<i>{album}</i>}` is an optional section, kept only if the value of `album` is not empty.
+/// `{{` and `}}` are literal braces. Fails on unknown or unclosed placeholders and sections.
+pub fn render<F>(template: &str, lookup: F) -> Result<String, Box<dyn Error>>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    render_chars(&mut template.chars().peekable(), &lookup, false)
+}
+
+/// Renders up to the end of the template, or up to the closing brace of a section if `in_section`
+fn render_chars<F>(chars: &mut Peekable<Chars>, lookup: &F, in_section: bool) -> Result<String, Box<dyn Error>>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '}' if in_section => return Ok(result),
+            '{' if chars.peek() == Some(&'?') => {
+                chars.next();
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some(':') => break,
+                        Some(v) => name.push(v),
+                        None => return Err(format!("unclosed section '{{?{}'", name).into()),
+                    }
+                }
+                // rendered even when left out, so its placeholders are always checked
+                let section = render_chars(chars, lookup, true)?;
+                match lookup(name.trim()) {
+                    Some(value) if value.is_empty() => {}
+                    Some(_) => result.push_str(&section),
+                    None => return Err(format!("unknown placeholder '{{?{}:'", name).into()),
+                }
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(v) => name.push(v),
+                        None => return Err(format!("unclosed placeholder '{{{}'", name).into()),
+                    }
+                }
+                match lookup(name.trim()) {
+                    Some(value) => result.push_str(&value),
+                    None => return Err(format!("unknown placeholder '{{{}}}'", name).into()),
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+
+    if in_section {
+        return Err("unclosed section, missing '}'".into());
+    }
+
+    Ok(result)
+}
+
+/// Formats microseconds as `m:ss`, or `h:mm:ss` if longer than an hour
+pub fn format_duration(microseconds: u64) -> String {
+    let seconds = microseconds / 1_000_000;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
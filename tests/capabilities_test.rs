@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod tests {
+    use mpris_widget::capabilities::Capabilities;
+
+    #[test]
+    fn parse_field() {
+        let capabilities = Capabilities::parse("next, play,seek");
+
+        assert!(capabilities.can_go_next && capabilities.can_play && capabilities.can_seek);
+        assert!(!capabilities.can_go_previous && !capabilities.can_pause);
+        assert!(Capabilities::parse("") == Capabilities::default(), "unknown allows everything");
+        assert!(!Capabilities::parse("none").can_play);
+    }
+
+    #[test]
+    fn fallbacks() {
+        let stream = Capabilities::parse("play");
+
+        assert_eq!(stream.resolve("play-pause", "Playing").unwrap(), "stop");
+        assert_eq!(stream.resolve("pause", "Playing").unwrap(), "stop");
+        assert_eq!(stream.resolve("play-pause", "Stopped").unwrap(), "play-pause");
+        assert!(stream.resolve("next", "Playing").is_err());
+        assert!(stream.resolve("position", "Playing").is_err());
+        assert_eq!(stream.resolve("volume", "Playing").unwrap(), "volume");
+
+        let paused = Capabilities::parse("pause");
+        assert_eq!(paused.resolve("play-pause", "Playing").unwrap(), "play-pause");
+        assert!(paused.resolve("play-pause", "Paused").is_err());
+    }
+}
@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod tests {
+    use mpris_widget::events::{diff, EventKind};
+    use mpris_widget::parse_players_metadata;
+    use std::time::Duration;
+
+    const SPOTIFY: &str = "Playing;Artist;Title;;Album;;spotify;spotify\n";
+    const FIREFOX: &str = "Paused;;Video;;;;firefox;firefox.instance33\n";
+
+    fn kinds(previous: &str, current: &str) -> Vec<EventKind> {
+        let previous = parse_players_metadata(previous).unwrap();
+        let current = parse_players_metadata(current).unwrap();
+
+        diff(&previous, &current, Duration::from_secs(1)).iter().map(|v| v.event).collect()
+    }
+
+    #[test]
+    fn no_change() {
+        let result = kinds(SPOTIFY, SPOTIFY);
+
+        assert!(result.is_empty(), "expected no event");
+    }
+
+    #[test]
+    fn player_added_and_removed() {
+        assert!(kinds(SPOTIFY, &(String::from(SPOTIFY) + FIREFOX)) == vec![EventKind::PlayerAdded]);
+        assert!(kinds(&(String::from(SPOTIFY) + FIREFOX), SPOTIFY) == vec![EventKind::PlayerRemoved]);
+    }
+
+    #[test]
+    fn track_and_state_changed() {
+        let result = kinds(SPOTIFY, "Paused;Artist;Other title;;Album;;spotify;spotify\n");
+
+        assert!(result == vec![EventKind::TrackChanged, EventKind::StateChanged]);
+    }
+
+    #[test]
+    fn seeked_and_volume_changed() {
+        let previous = "Playing;Artist;Title;;Album;;spotify;spotify;10000000;60000000;0.5\n";
+
+        let result = kinds(previous, "Playing;Artist;Title;;Album;;spotify;spotify;11000000;60000000;0.5\n");
+        assert!(result.is_empty(), "playback is not a seek");
+
+        let result = kinds(previous, "Playing;Artist;Title;;Album;;spotify;spotify;40000000;60000000;0.8\n");
+        assert!(result == vec![EventKind::Seeked, EventKind::VolumeChanged]);
+    }
+}
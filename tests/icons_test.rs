@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod tests {
+    use mpris_widget::icons::IconPreset;
+    use mpris_widget::parse_players_metadata;
+    use mpris_widget::settings::IconSettings;
+
+    #[test]
+    fn state_icons() {
+        let mut icons = IconSettings { preset: IconPreset::Ascii, ..IconSettings::default() };
+
+        assert_eq!(icons.get_state_icon("Playing"), ">");
+        assert_eq!(icons.get_state_icon("Paused"), "||");
+        assert_eq!(icons.get_state_icon("unknown"), "");
+
+        icons.paused = Some(String::from("P"));
+        assert_eq!(icons.get_state_icon("Paused"), "P");
+    }
+
+    #[test]
+    fn player_icons() {
+        let mut icons = IconSettings::default();
+
+        assert_eq!(icons.get_player_icon("spotify"), "\u{f1bc}");
+        assert_eq!(icons.get_player_icon("unknown"), "\u{f001}");
+
+        icons.players.insert(String::from("mpv"), String::from("M"));
+        icons.default_player = Some(String::from("?"));
+        assert_eq!(icons.get_player_icon("mpv"), "M");
+        assert_eq!(icons.get_player_icon("unknown"), "?");
+    }
+
+    #[test]
+    fn display_prefix() {
+        let players = parse_players_metadata("Playing;Artist;Title;;;;spotify;spotify\n").unwrap();
+
+        assert_eq!(players[0].get_display(), "\u{f144} Artist - Title");
+    }
+}
@@ -13,7 +13,7 @@ mod tests {
         // let result = mpris_widget::exec_action(&action, &player);
 
         if let Err(error) = result {
-            assert!(false, "'send_action' error: {}", error);
+            panic!("'send_action' error: {}", error);
         }
     }
 
@@ -23,10 +23,10 @@ mod tests {
         let action: String = String::from("unknown_command");
         let player = String::new();
 
-        let result = exec_action(&action, &player, false);
+        let result = tokio_test::block_on(exec_action(&action, &player, false));
 
         if let Err(error) = result {
-            assert!(false, "'send_action' error: {}", error);
+            panic!("'send_action' error: {}", error);
         }
     }
 
@@ -38,7 +38,7 @@ mod tests {
         let result = tokio_test::block_on(send_action(&action, &player, false, false));
 
         if let Err(error) = result {
-            assert!(false, "'send_action' error: {}", error);
+            panic!("'send_action' error: {}", error);
         }
     }
 
@@ -50,7 +50,7 @@ mod tests {
         let result = tokio_test::block_on(send_action(&action, &player, false, false));
 
         if let Err(error) = result {
-            assert!(false, "'send_action' error: {}", error);
+            panic!("'send_action' error: {}", error);
         }
     }
 
@@ -63,7 +63,7 @@ mod tests {
         let result = read_first_line(&file_path);
 
         if let Err(error) = result {
-            assert!(false, "'read_first_line' error: {}", error);
+            panic!("'read_first_line' error: {}", error);
         } else if let Ok(v) = result {
             assert!(v == expected, "first line was '{}', expected '{}'", v, expected);
         }
@@ -0,0 +1,275 @@
+#[cfg(test)]
+mod tests {
+    use mpris_widget::output::{
+        escape_lemonbar, escape_lemonbar_action, escape_pango, escape_polybar, escape_polybar_action, escape_tmux, escape_xmobar,
+        escape_yambar, get_art_path, get_click_action, get_click_player, Button, ClickEvent, Element, OutputFormat,
+    };
+    use mpris_widget::parse_players_metadata;
+    use mpris_widget::settings::Settings;
+
+    fn element(text: &str) -> Element<'_> {
+        Element { text, player: "spotify", state: "Playing", instance: "spotify", metadata: None, players: &[] }
+    }
+
+    #[test]
+    fn empty_text_is_empty_line() {
+        let settings = Settings::default();
+
+        assert_eq!(OutputFormat::Waybar.format(&element(""), &settings), "");
+        assert_eq!(OutputFormat::Polybar.format(&element(""), &settings), "");
+    }
+
+    #[test]
+    fn waybar_json() {
+        let mut settings = Settings::default();
+        settings.waybar.tooltip = String::from("({player}) {text}");
+
+        let result = OutputFormat::Waybar.format(&element("A \"B\""), &settings);
+
+        assert_eq!(
+            result,
+            r#"{"text": "A &quot;B&quot;", "class": ["custom-spotify", "playing"], "alt": "spotify", "tooltip": "(spotify) A &quot;B&quot;", "state": "playing", "instance": "spotify"}"#
+        );
+    }
+
+    #[test]
+    fn waybar_tooltip() {
+        let players = parse_players_metadata("Playing;A & B;<Title>;;Album;;mpv;mpv;61000000;180000000\n").unwrap();
+        let element = Element { text: "<Title>", player: "mpv", state: "Playing", instance: "mpv", metadata: players.first(), players: &players };
+
+        let result = OutputFormat::Waybar.format(&element, &Settings::default());
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+
+        assert_eq!(value["text"], "&lt;Title&gt;");
+        assert_eq!(value["tooltip"], "<b>&lt;Title&gt;</b>\nA &amp; B\n<i>Album</i>\n1:01 / 3:00\n<small>mpv (mpv)</small>");
+        assert_eq!(escape_pango("<a href='x'>&\"</a>"), "&lt;a href=&apos;x&apos;&gt;&amp;&quot;&lt;/a&gt;");
+    }
+
+    #[test]
+    fn waybar_tooltip_without_album_and_position() {
+        let players = parse_players_metadata("Playing;;Video;;;;firefox;firefox\n").unwrap();
+        let element = Element { text: "Video", player: "firefox", state: "Playing", instance: "firefox", metadata: players.first(), players: &players };
+
+        let result = OutputFormat::Waybar.format(&element, &Settings::default());
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+
+        assert_eq!(value["tooltip"], "<b>Video</b>\n<small>firefox (firefox)</small>");
+    }
+
+    #[test]
+    fn polybar_escaping() {
+        assert_eq!(escape_polybar("100% %{F#fff}"), "100%% %%{F#fff}");
+        assert_eq!(escape_polybar_action("/bin/a:b action next"), "/bin/a\\:b action next");
+        assert_eq!(escape_polybar_action("/bin/100%:b"), "/bin/100%\\:b");
+    }
+
+    #[test]
+    fn polybar_tags() {
+        let mut settings = Settings::default();
+        settings.colors.playing = Some(String::from("#00ff00"));
+        settings.colors.players.insert(String::from("spotify"), String::from("#1db954"));
+
+        let result = OutputFormat::Polybar.format(&element("50%: Title"), &settings);
+
+        assert!(result.contains("%{F#1db954}50%%: Title%{F-}"), "player color over state color: {}", result);
+        assert!(result.starts_with("%{A5:"), "scroll down is the outer tag: {}", result);
+        assert!(result.contains(" action play-pause:}%{F#1db954}"), "left click plays or pauses: {}", result);
+        assert_eq!(result.matches("%{A}").count(), 5);
+    }
+
+    #[test]
+    fn i3bar_blocks() {
+        let settings = Settings::default();
+
+        assert_eq!(OutputFormat::I3bar.format(&element(""), &settings), ",[]");
+
+        let result = OutputFormat::I3bar.format(&element("Title"), &settings);
+        assert!(result.starts_with(",[{"), "one array per line after the first: {}", result);
+        assert!(result.contains(r#""instance":"previous""#) && result.contains(r#""instance":"next""#));
+        assert!(result.contains(r#""instance":"play-pause","full_text":"Title""#));
+    }
+
+    #[test]
+    fn i3bar_button_blocks() {
+        let mut settings = Settings::default();
+        settings.icons.next = Some(String::from(">>"));
+        let players = parse_players_metadata("Playing;A;Title;;;;mpv;mpv;;;;;play,pause,next\n").unwrap();
+        let element = Element { text: "Title", player: "mpv", state: "Playing", instance: "mpv", metadata: players.first(), players: &players };
+
+        let result = OutputFormat::I3bar.format(&element, &settings);
+        let blocks: serde_json::Value = serde_json::from_str(&result[1..]).unwrap();
+
+        // no previous block, the player cannot go back
+        assert_eq!(blocks.as_array().unwrap().len(), 2, "{}", result);
+        assert_eq!(blocks[0]["instance"], "play-pause");
+        assert_eq!(blocks[1]["instance"], "next");
+        assert_eq!(blocks[1]["full_text"], ">>");
+        assert_eq!(blocks[1]["separator"], true);
+    }
+
+    #[test]
+    fn i3bar_click_actions() {
+        let event = |instance: &str, button: u8, modifiers: &[&str]| ClickEvent {
+            name: Some(String::from("mpris_widget")),
+            instance: Some(String::from(instance)),
+            button,
+            modifiers: modifiers.iter().map(|v| String::from(*v)).collect(),
+        };
+
+        assert_eq!(get_click_action(&event("previous", 1, &[])), Some("previous"));
+        assert_eq!(get_click_action(&event("play-pause", 1, &[])), Some("play-pause"));
+        assert_eq!(get_click_action(&event("play-pause", 1, &["Shift"])), Some("stop"));
+        assert_eq!(get_click_action(&event("next", 5, &[])), Some("scroll-down"));
+        assert_eq!(get_click_action(&event("next", 3, &[])), None);
+    }
+
+    #[test]
+    fn eww_line() {
+        let players = parse_players_metadata("Playing;Artist;Title;file:///tmp/a%20b.png;Album;;mpv;mpv;5000000;60000000\n").unwrap();
+        let element = Element { text: "Title", player: "mpv", state: "Playing", instance: "mpv", metadata: players.first(), players: &players };
+
+        let result = OutputFormat::Eww.format(&element, &Settings::default());
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+
+        assert_eq!(value["players"][0]["title"], "Title");
+        assert_eq!(value["selected"], "mpv");
+        assert_eq!(value["position"], 5000000);
+        assert_eq!(value["art_path"], "/tmp/a b.png");
+    }
+
+    #[test]
+    fn eww_escaping() {
+        let players = parse_players_metadata("Playing;A \\ B;Tab\there \"quoted\";;;;my\"player;my\"player\n").unwrap();
+        let element = Element { text: "", player: "my\"player", state: "Playing", instance: "my\"player", metadata: players.first(), players: &players };
+
+        let result = OutputFormat::Eww.format(&element, &Settings::default());
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+
+        assert_eq!(value["players"][0]["artist"], "A \\ B");
+        assert_eq!(value["players"][0]["title"], "Tab\there \"quoted\"");
+        assert_eq!(value["players"][0]["class"], "custom-my\"player");
+    }
+
+    #[test]
+    fn art_paths() {
+        assert_eq!(get_art_path("file:///home/me/cover%C3%A9.jpg"), "/home/me/coveré.jpg");
+        assert_eq!(get_art_path("https://example.org/cover.jpg"), "");
+        assert_eq!(get_art_path("file:///a%2"), "/a%2");
+    }
+
+    #[test]
+    fn tmux_style() {
+        let mut settings = Settings::default();
+
+        assert_eq!(escape_tmux("#1 #[fg=red]"), "##1 ##[fg=red]");
+        assert_eq!(OutputFormat::Tmux.format(&element("Title #1"), &settings), "Title ##1");
+
+        settings.colors.playing = Some(String::from("#a6e3a1"));
+        assert_eq!(OutputFormat::Tmux.format(&element("Title"), &settings), "#[fg=#a6e3a1]Title#[default]");
+    }
+
+    #[test]
+    fn lemonbar_tags() {
+        let mut settings = Settings::default();
+        settings.colors.paused = Some(String::from("#f9e2af"));
+
+        assert_eq!(escape_lemonbar("100%{F-}"), "100%%{F-}");
+        assert_eq!(escape_lemonbar_action("/a:b action next"), "/a\\:b action next");
+
+        let element = Element { state: "Paused", ..element("50%") };
+        let result = OutputFormat::Lemonbar.format(&element, &settings);
+
+        assert!(result.contains("%{F#f9e2af}50%%%{F-}"), "{}", result);
+        assert!(result.starts_with("%{A5:"), "{}", result);
+        assert_eq!(result.matches("%{A}").count(), 5);
+    }
+
+    #[test]
+    fn xmobar_markup() {
+        assert_eq!(escape_xmobar("<fc=red>a</fc>"), "<raw=1:</>fc=red>a<raw=1:</>/fc>");
+
+        let result = OutputFormat::Xmobar.format(&element("a < b"), &Settings::default());
+
+        assert!(result.contains(" action play-pause` button=1>a <raw=1:</> b</action>"), "{}", result);
+        assert_eq!(result.matches("</action>").count(), 5);
+    }
+
+    #[test]
+    fn yambar_tags() {
+        let players = parse_players_metadata("Playing;Artist;Title;;Album;;mpv;mpv\n").unwrap();
+        let element = Element { text: "Artist - Title\n", player: "mpv", state: "Playing", instance: "mpv", metadata: players.first(), players: &players };
+
+        let result = OutputFormat::Yambar.format(&element, &Settings::default());
+
+        assert_eq!(escape_yambar("a\nb"), "a b");
+        assert!(result.starts_with("text|string|Artist - Title \n"), "{}", result);
+        assert!(result.contains("playing|bool|true\n"));
+        assert!(result.contains("title|string|Title\n"));
+        assert!(result.ends_with("\n"));
+        assert!(OutputFormat::Yambar.format(&element_empty(), &Settings::default()).contains("playing|bool|false"));
+    }
+
+    #[test]
+    fn button_modules() {
+        let players = parse_players_metadata("Playing;;Radio;;;;mpv;mpv;;;;;play,pause\n").unwrap();
+        let element = Element { text: "Radio", player: "mpv", state: "Playing", instance: "mpv", metadata: players.first(), players: &players };
+        let settings = Settings::default();
+
+        assert_eq!(
+            OutputFormat::Waybar.format_button(Button::Next, &element, &settings),
+            "{\"text\": \"\u{f051}\", \"class\": [\"custom-mpv\", \"next\", \"disabled\"], \"alt\": \"next\"}"
+        );
+        assert!(!OutputFormat::Waybar.format_button(Button::PlayPause, &element, &settings).contains("disabled"));
+        assert_eq!(OutputFormat::Tmux.format_button(Button::Previous, &element, &settings), "");
+        assert_eq!(OutputFormat::Tmux.format_button(Button::PlayPause, &element, &settings), "\u{f144}");
+        assert_eq!(OutputFormat::Tmux.format_button(Button::Next, &element_empty(), &settings), "");
+    }
+
+    #[test]
+    fn all_players() {
+        let players = parse_players_metadata("Playing;Band;Song;;;;spotify;spotify\nPaused;;50% off;;;;firefox;firefox.instance2\n").unwrap();
+        let displays: Vec<String> = players.iter().map(|v| v.get_display()).collect();
+        let elements: Vec<Element> = players
+            .iter()
+            .zip(displays.iter())
+            .map(|(data, text)| Element { text, player: &data.player, state: "Playing", instance: &data.instance, metadata: Some(data), players: &players })
+            .collect();
+        let settings = Settings::default();
+
+        assert_eq!(OutputFormat::Tmux.format_all(&elements, 1, &settings), "\u{f144} Band - Song | [\u{f28b} 50% off]");
+        assert_eq!(OutputFormat::Tmux.format_all(&[], 0, &settings), "");
+
+        let result = OutputFormat::Polybar.format_all(&elements, 0, &settings);
+        assert!(result.contains(" action next spotify:}"), "{}", result);
+        assert!(result.contains(" action play-pause firefox.instance2:}\u{f28b} 50%% off%{A}"), "{}", result);
+        assert_eq!(result.matches("%{A}").count(), 10);
+
+        let result = OutputFormat::Waybar.format_all(&elements, 1, &settings);
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(value["text"], "\u{f144} Band - Song | [\u{f28b} 50% off]");
+        assert_eq!(value["alt"], "firefox");
+
+        let result = OutputFormat::I3bar.format_all(&elements, 0, &settings);
+        let blocks: serde_json::Value = serde_json::from_str(result.trim_start_matches(',')).unwrap();
+        assert_eq!(blocks[0]["instance"], "player:spotify");
+        assert_eq!(blocks[0]["full_text"], "[\u{f144} Band - Song]");
+        assert_eq!(blocks[1]["full_text"], "\u{f28b} 50% off");
+    }
+
+    #[test]
+    fn i3bar_player_blocks() {
+        let event = ClickEvent {
+            name: Some(String::from("mpris_widget")),
+            instance: Some(String::from("player:firefox.instance2")),
+            button: 3,
+            modifiers: vec![],
+        };
+
+        assert_eq!(get_click_action(&event), Some("next"));
+        assert_eq!(get_click_player(&event), Some("firefox.instance2"));
+    }
+
+    fn element_empty() -> Element<'static> {
+        Element { text: "", player: "", state: "", instance: "", metadata: None, players: &[] }
+    }
+}
@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use mpris_widget::events::diff;
+    use mpris_widget::parse_players_metadata;
+    use mpris_widget::playback::{get_interrupted, Interruption, PlaybackStack};
+    use mpris_widget::settings::PlaybackSettings;
+    use std::time::Duration;
+
+    fn interrupted(settings: &PlaybackSettings, previous: &str, current: &str) -> Vec<String> {
+        let previous = parse_players_metadata(previous).unwrap();
+        let current = parse_players_metadata(current).unwrap();
+        let changes = diff(&previous, &current, Duration::from_secs(1));
+
+        get_interrupted(settings, &changes, &current).iter().map(|v| v.instance.clone()).collect()
+    }
+
+    #[test]
+    fn pause_the_others() {
+        let settings = PlaybackSettings { exclusive: true, coexist: vec![String::from("noise")], resume: false };
+        let previous = "Playing;A;B;;;;spotify;spotify\nPlaying;;Rain;;;;noise;noise\nPaused;;Video;;;;firefox;firefox\n";
+        let current = "Playing;A;B;;;;spotify;spotify\nPlaying;;Rain;;;;noise;noise\nPlaying;;Video;;;;firefox;firefox\n";
+
+        assert_eq!(interrupted(&settings, previous, current), vec!["spotify"]);
+        assert!(interrupted(&settings, current, current).is_empty(), "nothing started");
+    }
+
+    #[test]
+    fn coexisting_player_starts() {
+        let settings = PlaybackSettings { exclusive: true, coexist: vec![String::from("noise")], resume: false };
+        let previous = "Playing;A;B;;;;spotify;spotify\n";
+        let current = "Playing;A;B;;;;spotify;spotify\nPlaying;;Rain;;;;noise;noise\n";
+
+        assert!(interrupted(&settings, previous, current).is_empty());
+    }
+
+    fn interruption(paused: &str, by: &str) -> Interruption {
+        Interruption {
+            paused: String::from(paused),
+            by: String::from(by),
+            selected_player: String::from(paused),
+            selected_instance: String::from(paused),
+            selection: "selected",
+        }
+    }
+
+    fn ended(stack: &mut PlaybackStack, previous: &str, current: &str) -> Vec<String> {
+        let previous = parse_players_metadata(previous).unwrap();
+        let current = parse_players_metadata(current).unwrap();
+        let changes = diff(&previous, &current, Duration::from_secs(1));
+
+        stack.take_ended(&PlaybackSettings::default(), &changes, &current).into_iter().map(|v| v.paused).collect()
+    }
+
+    #[test]
+    fn resume_in_order() {
+        let mut stack = PlaybackStack::default();
+        stack.push(interruption("spotify", "firefox"));
+        stack.push(interruption("firefox", "mpv"));
+
+        let all = "Paused;A;B;;;;spotify;spotify\nPaused;;Clip;;;;firefox;firefox\nPlaying;;C;;;;mpv;mpv\n";
+        let without_mpv = "Paused;A;B;;;;spotify;spotify\nPaused;;Clip;;;;firefox;firefox\n";
+        let firefox_playing = "Paused;A;B;;;;spotify;spotify\nPlaying;;Clip;;;;firefox;firefox\n";
+        let firefox_stopped = "Paused;A;B;;;;spotify;spotify\nStopped;;Clip;;;;firefox;firefox\n";
+
+        assert!(ended(&mut stack, all, all).is_empty());
+        // mpv vanished: firefox first, spotify once firefox stops
+        assert_eq!(ended(&mut stack, all, without_mpv), vec!["firefox"]);
+        assert!(ended(&mut stack, without_mpv, firefox_playing).is_empty());
+        assert_eq!(ended(&mut stack, firefox_playing, firefox_stopped), vec!["spotify"]);
+    }
+
+    #[test]
+    fn resumed_by_the_user() {
+        let mut stack = PlaybackStack::default();
+        stack.push(interruption("spotify", "firefox"));
+
+        let paused = "Paused;A;B;;;;spotify;spotify\nPlaying;;Clip;;;;firefox;firefox\n";
+        let resumed = "Playing;A;B;;;;spotify;spotify\nPlaying;;Clip;;;;firefox;firefox\n";
+        assert!(ended(&mut stack, resumed, resumed).is_empty(), "not paused yet");
+        assert!(ended(&mut stack, resumed, paused).is_empty());
+        assert!(ended(&mut stack, paused, resumed).is_empty());
+        assert!(ended(&mut stack, resumed, "Paused;A;B;;;;spotify;spotify\n").is_empty(), "forgotten");
+
+        stack.push(interruption("spotify", "firefox"));
+        assert!(ended(&mut stack, paused, "Paused;A;B;;;;spotify;spotify\nPlaying;;Other;;;;vlc;vlc\n").is_empty(), "another one plays");
+    }
+}
@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use mpris_widget::parse_players_metadata;
+    use mpris_widget::rules::{apply, Rule, RuleField};
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Rules {
+        rules: Vec<Rule>,
+    }
+
+    fn rules(content: &str) -> Vec<Rule> {
+        toml::from_str::<Rules>(content).unwrap().rules
+    }
+
+    const RULES: &str = r#"
+        [[rules]]
+        players = ["firefox"]
+        field = "title"
+        find = '\s*-\s*YouTube$'
+
+        [[rules]]
+        field = "title"
+        find = '\s*\(Official (Music )?Video\)'
+
+        [[rules]]
+        field = "artist"
+        find = '^(.+)VEVO$'
+        replace = "$1"
+    "#;
+
+    #[test]
+    fn rewrite_in_order() {
+        let mut players = parse_players_metadata("Playing;ArtistVEVO;Song (Official Video) - YouTube;;;;firefox;firefox\n").unwrap();
+
+        apply(&rules(RULES), &mut players[0]);
+
+        assert_eq!(players[0].get_display(), "\u{f144} Artist - Song");
+    }
+
+    #[test]
+    fn scoped_by_player() {
+        let mut players = parse_players_metadata("Playing;Band;Track - YouTube;;;;spotify;spotify\n").unwrap();
+
+        apply(&rules(RULES), &mut players[0]);
+
+        assert_eq!(players[0].get_display(), "\u{f144} Band - Track - YouTube");
+    }
+
+    #[test]
+    fn trimmed_only_when_matched() {
+        let mut players = parse_players_metadata("Playing;ArtistVEVO;Song;;Album;;spotify;spotify\n").unwrap();
+        *RuleField::Title.get_mut(&mut players[0]) = String::from("  Song  ");
+        *RuleField::Artist.get_mut(&mut players[0]) = String::from(" ArtistVEVO");
+
+        apply(&rules(RULES), &mut players[0]);
+
+        // no rule matches the title, the artist rule does
+        assert_eq!(RuleField::Title.get(&players[0]), "  Song  ");
+        assert_eq!(RuleField::Artist.get(&players[0]), "Artist");
+    }
+
+    #[test]
+    fn invalid_regex() {
+        let result = toml::from_str::<Rules>("[[rules]]\nfield = \"title\"\nfind = \"((\"\n");
+
+        assert!(result.is_err(), "invalid regex should be an error");
+    }
+}
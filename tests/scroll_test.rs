@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod tests {
+    use mpris_widget::scroll::{get_playerctl_args, get_step, ScrollMode};
+    use mpris_widget::settings::MouseSettings;
+
+    #[test]
+    fn scroll_steps() {
+        assert_eq!(get_step("scroll-up"), Some(1));
+        assert_eq!(get_step("scroll-down"), Some(-1));
+        assert_eq!(get_step("next"), None);
+    }
+
+    #[test]
+    fn burst_is_one_call() {
+        let settings = MouseSettings::default();
+
+        assert_eq!(get_playerctl_args(ScrollMode::Seek, 3, &settings), Some(vec![String::from("position"), String::from("15+")]));
+        assert_eq!(get_playerctl_args(ScrollMode::Volume, -2, &settings), Some(vec![String::from("volume"), String::from("0.10-")]));
+        assert_eq!(get_playerctl_args(ScrollMode::Track, 4, &settings), Some(vec![String::from("next")]));
+    }
+
+    #[test]
+    fn nothing_to_do() {
+        let settings = MouseSettings::default();
+
+        assert_eq!(get_playerctl_args(ScrollMode::Volume, 0, &settings), None);
+        assert_eq!(get_playerctl_args(ScrollMode::None, 1, &settings), None);
+    }
+
+    #[test]
+    fn scroll_mode_by_player() {
+        let mut settings = MouseSettings::default();
+        settings.players.insert(String::from("mpv"), ScrollMode::Seek);
+
+        assert!(settings.get_scroll_mode("mpv") == ScrollMode::Seek);
+        assert!(settings.get_scroll_mode("spotify") == ScrollMode::Volume);
+    }
+}
@@ -0,0 +1,32 @@
+#[cfg(test)]
+mod tests {
+    use mpris_widget::parse_players_metadata;
+    use mpris_widget::selection::pick_player;
+
+    const PLAYERS: &str = "Playing;A;B;;;;spotify;spotify\nPaused;;Video;;;;firefox;firefox.instance1\nPaused;;Other;;;;firefox;firefox.instance2\n";
+
+    #[test]
+    fn selected_instance() {
+        let players = parse_players_metadata(PLAYERS).unwrap();
+
+        assert_eq!(pick_player(&players, "firefox.instance2", "firefox"), 2);
+        assert_eq!(pick_player(&players, "spotify", "firefox"), 0);
+    }
+
+    #[test]
+    fn other_instance_of_the_player() {
+        let players = parse_players_metadata(PLAYERS).unwrap();
+
+        // e.g.: the selected instance was closed
+        assert_eq!(pick_player(&players, "firefox.instance3", "firefox"), 1);
+    }
+
+    #[test]
+    fn first_player() {
+        let players = parse_players_metadata(PLAYERS).unwrap();
+
+        assert_eq!(pick_player(&players, "vlc", "vlc"), 0);
+        assert_eq!(pick_player(&players, "", ""), 0);
+        assert_eq!(pick_player(&[], "spotify", "spotify"), 0);
+    }
+}
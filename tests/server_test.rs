@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod tests {
+    use mpris_widget::{read_stream_messages, ClientLimits, StreamMessage, Subscriptions};
+    use std::error::Error;
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+    use tokio::sync::mpsc;
+    use tokio::time;
+
+    /// Writes `input` from a client that then stops writing, returns what the server read
+    async fn read_messages(input: &[u8]) -> (Result<(), Box<dyn Error>>, Vec<(String, String)>) {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        client.write_all(input).await.unwrap();
+        client.shutdown().await.unwrap();
+
+        let (tx, mut rx) = mpsc::channel::<StreamMessage>(32);
+        let limits = ClientLimits::default();
+        let permit = limits.try_acquire_client().unwrap();
+        let result = read_stream_messages(server, &tx, &Subscriptions::default(), &limits, permit).await;
+
+        drop(tx);
+        let mut messages = vec![];
+        while let Some(message) = rx.recv().await {
+            messages.push((String::from(message.action()), String::from(message.player())));
+        }
+
+        (result, messages)
+    }
+
+    fn message(action: &str, player: &str) -> (String, String) {
+        (String::from(action), String::from(player))
+    }
+
+    #[tokio::test]
+    async fn newline_framing() {
+        let (result, messages) = read_messages(b"next spotify\r\n\nprevious").await;
+
+        assert!(result.is_ok());
+        // empty lines are skipped, the last message may end without a newline
+        assert_eq!(messages, vec![message("next", "spotify"), message("previous", "")]);
+    }
+
+    #[tokio::test]
+    async fn several_messages() {
+        let (result, messages) = read_messages(b"play-pause\nnext firefox\nprevious\n").await;
+
+        assert!(result.is_ok());
+        assert_eq!(messages, vec![message("play-pause", ""), message("next", "firefox"), message("previous", "")]);
+    }
+
+    #[tokio::test]
+    async fn message_length_limit() {
+        let longest = "a".repeat(4095) + "\n";
+        let (result, messages) = read_messages(longest.as_bytes()).await;
+        assert!(result.is_ok());
+        assert_eq!(messages.len(), 1);
+
+        let too_long = "a".repeat(4096) + "\nnext\n";
+        let (result, messages) = read_messages(too_long.as_bytes()).await;
+        assert!(result.unwrap_err().to_string().contains("longer than 4096 bytes"));
+        assert!(messages.is_empty());
+    }
+
+    #[tokio::test]
+    async fn silent_client_times_out() {
+        let (_client, server) = UnixStream::pair().unwrap();
+        let (tx, _rx) = mpsc::channel::<StreamMessage>(1);
+        let limits = ClientLimits::default();
+        let permit = limits.try_acquire_client().unwrap();
+
+        let result = read_stream_messages(server, &tx, &Subscriptions::default(), &limits, permit).await;
+
+        assert_eq!(result.unwrap_err().to_string(), "client timed out");
+    }
+
+    #[tokio::test]
+    async fn invalid_utf8() {
+        let (result, messages) = read_messages(b"next\nplay \xff\xfe\n").await;
+
+        assert!(result.is_err());
+        assert_eq!(messages, vec![message("next", "")]);
+    }
+
+    #[tokio::test]
+    async fn sink_subscriber_gets_the_last_line() {
+        let subscriptions = Subscriptions::default();
+        subscriptions.get_sink("obs").send_replace(String::from("Artist - Title"));
+
+        // subscribes after the line was written
+        let (mut client, server) = UnixStream::pair().unwrap();
+        client.write_all(b"subscribe sink:obs\n").await.unwrap();
+
+        let (tx, _rx) = mpsc::channel::<StreamMessage>(1);
+        let limits = ClientLimits::default();
+        let permit = limits.try_acquire_client().unwrap();
+        let server_subscriptions = subscriptions.clone();
+        tokio::spawn(async move {
+            let _ = read_stream_messages(server, &tx, &server_subscriptions, &limits, permit).await;
+        });
+
+        let mut reader = BufReader::new(client);
+        let mut line = String::new();
+        time::timeout(Duration::from_secs(1), reader.read_line(&mut line)).await.unwrap().unwrap();
+        assert_eq!(line, "Artist - Title\n");
+
+        // then each new line
+        subscriptions.get_sink("obs").send_replace(String::from("Other - Song"));
+        line.clear();
+        time::timeout(Duration::from_secs(1), reader.read_line(&mut line)).await.unwrap().unwrap();
+        assert_eq!(line, "Other - Song\n");
+    }
+}
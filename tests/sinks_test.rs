@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod tests {
+    use mpris_widget::settings::{SinkFilter, SinkKind, Settings};
+    use std::{env, fs};
+
+    fn load(content: &str) -> Result<Settings, String> {
+        let path = env::temp_dir().join(format!("mpris_widget_sinks_test_{}.toml", std::process::id()));
+        fs::write(&path, content).unwrap();
+        env::set_var("MPRIS_WIDGET_CONFIG", &path);
+
+        let result = Settings::load().map_err(|err| err.to_string());
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    // one test, the configuration path is in the environment of the process
+    #[test]
+    fn sink_settings() {
+        let settings = load(
+            "[[sinks]]\ntype = \"file\"\npath = \"/tmp/now_playing.txt\"\ntemplate = \"{artist} - {title}\"\nfilter = \"track\"\n\
+             [[sinks]]\ntype = \"socket\"\nname = \"obs\"\nformat = \"tmux\"\nmin_interval = 500\n",
+        )
+        .unwrap();
+
+        assert_eq!(settings.sinks.len(), 2);
+        assert!(settings.sinks[0].kind == SinkKind::File && settings.sinks[0].filter == SinkFilter::Track);
+        assert!(settings.sinks[1].filter == SinkFilter::Content && settings.sinks[1].min_interval == 500);
+
+        let result = load("[[sinks]]\ntype = \"fifo\"\ntemplate = \"{title}\"\n");
+        assert!(result.is_err_and(|err| err.contains("'path' is required")));
+
+        let result = load("[[sinks]]\ntype = \"socket\"\nname = \"obs\"\n");
+        assert!(result.is_err_and(|err| err.contains("either 'format' or 'template'")));
+
+        let result = load("[[sinks]]\ntype = \"file\"\npath = \"/tmp/a\"\ntemplate = \"{unknown}\"\n");
+        assert!(result.is_err_and(|err| err.contains("unknown placeholder")));
+    }
+}
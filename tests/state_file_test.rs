@@ -0,0 +1,39 @@
+#[cfg(test)]
+mod tests {
+    use mpris_widget::state_file::{read_output_file_player, StateFile};
+    use mpris_widget::status::StatusMetadata;
+    use std::{env, fs};
+
+    fn write(name: &str, content: &str) -> String {
+        let path = env::temp_dir().join(format!("mpris_widget_{}_{}", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn read_json_state_file() {
+        let state_file = StateFile {
+            player: String::from("firefox"),
+            instance: String::from("firefox.instance33"),
+            state: String::from("Playing"),
+            metadata: StatusMetadata::default(),
+            timestamp: 0,
+        };
+        let path = write("state.json", &serde_json::to_string(&state_file).unwrap());
+
+        let result = read_output_file_player(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result, "firefox.instance33");
+    }
+
+    #[test]
+    fn read_legacy_output_file() {
+        let path = write("output.txt", "spotify\nignored\n");
+
+        let result = read_output_file_player(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result, "spotify");
+    }
+}
@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod tests {
+    use mpris_widget::parse_players_metadata;
+    use mpris_widget::settings::StreamSettings;
+    use mpris_widget::streams::{apply, get_station};
+
+    fn settings() -> StreamSettings {
+        StreamSettings { split_title: vec![String::from("mpv")], ..StreamSettings::default() }
+    }
+
+    #[test]
+    fn split_stream_title() {
+        let mut players = parse_players_metadata(
+            "Playing;;Band - Song - Live;;;;mpv;mpv;;;;https://user@radio.example.org:8000/stream.mp3\n\
+             Playing;;Band - Song;;;;vlc;vlc\n",
+        )
+        .unwrap();
+
+        apply(&settings(), &mut players[0]);
+        apply(&settings(), &mut players[1]);
+
+        assert_eq!(players[0].get_display(), "\u{f144} Band - Song - Live");
+        let value = serde_json::to_value(&players[0]).unwrap();
+        assert_eq!(value["artist"], "Band");
+        assert_eq!(value["title"], "Song - Live");
+        assert_eq!(value["station"], "radio.example.org");
+
+        let value = serde_json::to_value(&players[1]).unwrap();
+        assert_eq!(value["artist"], "", "vlc is not listed");
+    }
+
+    #[test]
+    fn artist_kept() {
+        let mut players = parse_players_metadata("Playing;Band;Intro - Part 1;;Album;;mpv;mpv\n").unwrap();
+
+        apply(&settings(), &mut players[0]);
+
+        assert_eq!(players[0].get_display(), "\u{f144} Intro - Part 1", "mpv shows only the title");
+    }
+
+    #[test]
+    fn stations() {
+        assert_eq!(get_station("Radio Paradise", "http://stream.example.org/aac"), "Radio Paradise");
+        assert_eq!(get_station("", "icy://stream.example.org:8080"), "stream.example.org");
+        assert_eq!(get_station("", "file:///music/a.mp3"), "");
+        assert_eq!(get_station("", ""), "");
+    }
+}
@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod tests {
+    use mpris_widget::template::{format_duration, render};
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "artist" => Some(String::from("Artist")),
+            "title" => Some(String::from("Title")),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn render_placeholders() {
+        let result = render("{artist} - {title}", lookup).unwrap();
+
+        assert_eq!(result, "Artist - Title");
+    }
+
+    #[test]
+    fn render_literal_braces() {
+        let result = render("{{{title}}}", lookup).unwrap();
+
+        assert_eq!(result, "{Title}");
+    }
+
+    #[test]
+    fn render_unknown_placeholder() {
+        let result = render("{artist} {unknown}", lookup);
+
+        assert!(result.is_err(), "unknown placeholder should be an error");
+    }
+
+    #[test]
+    fn render_unclosed_placeholder() {
+        let result = render("{title", lookup);
+
+        assert!(result.is_err(), "unclosed placeholder should be an error");
+    }
+
+    #[test]
+    fn render_optional_sections() {
+        let lookup = |name: &str| match name {
+            "album" => Some(String::new()),
+            _ => lookup(name),
+        };
+
+        assert_eq!(render("{title}{?artist: by {artist}}", lookup).unwrap(), "Title by Artist");
+        assert_eq!(render("{title}{?album: on {album}}", lookup).unwrap(), "Title");
+        assert_eq!(render("{?artist:{?album:{album} }{{{title}}}}", lookup).unwrap(), "{Title}");
+    }
+
+    #[test]
+    fn render_invalid_sections() {
+        assert!(render("{?title:{title}", lookup).is_err(), "unclosed section should be an error");
+        assert!(render("{?title", lookup).is_err(), "section without a body should be an error");
+        assert!(render("{?unknown:{title}}", lookup).is_err(), "unknown placeholder should be an error");
+        assert!(render("{?title:{unknown}}", lookup).is_err(), "unknown placeholder in a section should be an error");
+    }
+
+    #[test]
+    fn format_durations() {
+        assert_eq!(format_duration(0), "0:00");
+        assert_eq!(format_duration(61_500_000), "1:01");
+        assert_eq!(format_duration(3_725_000_000), "1:02:05");
+    }
+}